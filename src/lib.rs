@@ -2,8 +2,23 @@
 
 use serde::{Serialize, Deserialize};
 use std::cmp::{Ord, Ordering};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
-use log::debug;
+use std::fmt;
+use std::io::Write;
+use std::ops::{Index, IndexMut};
+use std::path::PathBuf;
+use std::str::FromStr;
+use log::{debug, info, warn};
+use statrs::distribution::{ChiSquared, ContinuousCDF, FisherSnedecor, Normal, StudentsT};
+
+#[cfg(feature = "protobuf")]
+pub mod proto;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(all(feature = "protobuf", not(target_arch = "wasm32")))]
+pub use proto::write_stats_proto;
 
 #[derive(Debug, Clone, Default, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -30,6 +45,126 @@ pub struct Record {
     pub ptv_dp_phase_iii: Option<f64>,
 }
 
+impl Record {
+    /// Names of the ROIs stored in a record.
+    pub const ROI_NAMES: [&'static str; 3] = ["GTV", "GTV_N", "PTV_DP"];
+
+    /// Create a complete record from the volumes of phase 1, 2 and 3 of each ROI.
+    ///
+    /// # Arguments
+    ///
+    /// * `patient_id` - unique identifier
+    /// * `gtv` - GTV volumes
+    /// * `gtv_n` - GTV_N volumes
+    /// * `ptv_dp` - PTV_DP volumes
+    pub fn synthetic(
+        patient_id: &str,
+        gtv: [f64; 3],
+        gtv_n: [f64; 3],
+        ptv_dp: [f64; 3],
+    ) -> Record {
+        Self::synthetic_partial(patient_id, Some(gtv), Some(gtv_n), Some(ptv_dp))
+    }
+
+    /// Create a record from the volumes of phase 1, 2 and 3 of each ROI, where the volumes of
+    /// a ROI can be missing.
+    ///
+    /// # Arguments
+    ///
+    /// * `patient_id` - unique identifier
+    /// * `gtv` - GTV volumes
+    /// * `gtv_n` - GTV_N volumes
+    /// * `ptv_dp` - PTV_DP volumes
+    pub fn synthetic_partial(
+        patient_id: &str,
+        gtv: Option<[f64; 3]>,
+        gtv_n: Option<[f64; 3]>,
+        ptv_dp: Option<[f64; 3]>,
+    ) -> Record {
+        let phase = |v: Option<[f64; 3]>, i: usize| v.map(|v| v[i]);
+        Record {
+            patient_id: patient_id.to_string(),
+            gtv_phase_i: phase(gtv, 0),
+            gtv_phase_ii: phase(gtv, 1),
+            gtv_phase_iii: phase(gtv, 2),
+            gtv_n_phase_i: phase(gtv_n, 0),
+            gtv_n_phase_ii: phase(gtv_n, 1),
+            gtv_n_phase_iii: phase(gtv_n, 2),
+            ptv_dp_phase_i: phase(ptv_dp, 0),
+            ptv_dp_phase_ii: phase(ptv_dp, 1),
+            ptv_dp_phase_iii: phase(ptv_dp, 2),
+        }
+    }
+
+    /// Volume of a ROI in a treatment phase.
+    ///
+    /// Returns None if the volume is missing or if the ROI or phase is unknown.
+    ///
+    /// # Arguments
+    ///
+    /// * `roi` - ROI name [GTV, GTV_N or PTV_DP]
+    /// * `phase` - treatment phase [1, 2 or 3]
+    pub fn roi_volume(&self, roi: &str, phase: usize) -> Option<f64> {
+        match (roi, phase) {
+            ("GTV", 1) => self.gtv_phase_i,
+            ("GTV", 2) => self.gtv_phase_ii,
+            ("GTV", 3) => self.gtv_phase_iii,
+            ("GTV_N", 1) => self.gtv_n_phase_i,
+            ("GTV_N", 2) => self.gtv_n_phase_ii,
+            ("GTV_N", 3) => self.gtv_n_phase_iii,
+            ("PTV_DP", 1) => self.ptv_dp_phase_i,
+            ("PTV_DP", 2) => self.ptv_dp_phase_ii,
+            ("PTV_DP", 3) => self.ptv_dp_phase_iii,
+            _ => None,
+        }
+    }
+
+    /// Returns true if all the volumes of both records are equal.
+    fn has_same_volumes(&self, other: &Record) -> bool {
+        Record::ROI_NAMES.iter().all(|roi| {
+            (1..=3).all(|phase| self.roi_volume(roi, phase) == other.roi_volume(roi, phase))
+        })
+    }
+
+    /// Returns true if the volumes of a ROI are present in all three phases.
+    fn has_complete_volumes(&self, roi: &str) -> bool {
+        (1..=3).all(|phase| self.roi_volume(roi, phase).is_some_and(|x| !x.is_nan()))
+    }
+}
+
+/// Check that a ROI and treatment phase are stored in a record.
+fn validate_roi_phase(roi: &str, phase: usize) -> Result<(), Box<dyn Error>> {
+    if !Record::ROI_NAMES.contains(&roi) {
+        return Err(format!(
+            "Unknown ROI [{}], expected one of {:?}.",
+            roi,
+            Record::ROI_NAMES
+        )
+            .into());
+    }
+    if !(1..=3).contains(&phase) {
+        return Err(format!("Unknown treatment phase [{}], expected 1, 2 or 3.", phase).into());
+    }
+    Ok(())
+}
+
+/// Median of a set of values, NaN values are discarded.
+///
+/// Returns NaN if there are no values.
+fn median(v: &[f64]) -> f64 {
+    let mut v: Vec<_> = v.iter().copied().filter(|x| !x.is_nan()).collect();
+    if v.is_empty() {
+        return f64::NAN;
+    }
+    v.sort_by(f64::total_cmp);
+    let m = v.len() / 2;
+    if v.len() % 2 == 0 {
+        (v[m - 1] + v[m]) / 2.0
+    } else {
+        v[m]
+    }
+}
+
 /// Compute the average and standard deviation of the difference between of vectors (v1-v2).
 ///
 /// # Arguments
@@ -41,8 +176,8 @@ pub struct Record {
 /// Returns a tuple of (average, standard deviation, number of actual values used for the
 /// calculation).
 fn avg_std_dev_from_vectors(
-    v1: &Vec<Option<f64>>,
-    v2: &Vec<Option<f64>>,
+    v1: &[Option<f64>],
+    v2: &[Option<f64>],
 ) -> Result<(f64, f64, usize), Box<dyn Error>> {
     let n = v1.len();
     let m = v2.len();
@@ -65,18 +200,148 @@ fn avg_std_dev_from_vectors(
         })
         .filter(|x| !x.is_nan())
         .collect();
-    let n = v.len() as f64;
-    if n != m as f64 {
-        debug!("n: {}", n);
+    if v.len() != m {
+        debug!("n: {}", v.len());
         debug!("m: {}", m);
         debug!("v: {:#?}", v);
     }
+    Ok(avg_std_dev(&v))
+}
+
+/// Compute the average and corrected sample standard deviation of the relative volume changes
+/// `(v2 - v1) / v1 * 100` [%] between two vectors.
+///
+/// # Arguments
+///
+/// * `v1` - vector with optional volumes at the start phase
+/// * `v2` - vector with optional volumes at the end phase
+///
+/// See [avg_std_dev_pct] for the pairs which are skipped.
+fn avg_std_dev_pct_from_vectors(v1: &[Option<f64>], v2: &[Option<f64>]) -> (f64, f64, usize) {
+    avg_std_dev_pct(v1.iter().zip(v2.iter()).filter_map(|(o1, o2)| Some(((*o1)?, (*o2)?))))
+}
+
+/// Compute the average and corrected sample standard deviation of the relative volume changes
+/// `(end - start) / start * 100` [%] of (start, end) volume pairs.
+///
+/// Pairs with a NaN volume or a zero start volume are skipped. Returns a tuple of (average,
+/// standard deviation, number of pairs used for the calculation), which is zero-filled if no
+/// pair is left.
+fn avg_std_dev_pct(pairs: impl IntoIterator<Item = (f64, f64)>) -> (f64, f64, usize) {
+    let v: Vec<_> = pairs
+        .into_iter()
+        .filter(|(start, end)| *start != 0.0 && !start.is_nan() && !end.is_nan())
+        .map(|(start, end)| (end - start) / start * 100.0)
+        .collect();
+    if v.is_empty() {
+        return (0.0, 0.0, 0);
+    }
+    avg_std_dev(&v)
+}
+
+/// Compute the average and corrected sample standard deviation of a set of values.
+///
+/// Returns a tuple of (average, standard deviation, number of values).
+fn avg_std_dev(v: &[f64]) -> (f64, f64, usize) {
+    let n = v.len() as f64;
     let avg = v.iter().sum::<f64>() / n;
     let std_dev = (v.iter()
         .map(|d| f64::powf(d - avg, 2.0))
         .sum::<f64>() / (n - 1.0)
     ).sqrt();
-    Ok((avg, std_dev, n as usize))
+    (avg, std_dev, n as usize)
+}
+
+/// Ordinary least squares fit of `y = intercept + slope * x`.
+///
+/// Returns a tuple of (intercept, slope, coefficient of determination).
+fn linear_regression(x: &[f64], y: &[f64]) -> (f64, f64, f64) {
+    let n = x.len() as f64;
+    let avg_x = x.iter().sum::<f64>() / n;
+    let avg_y = y.iter().sum::<f64>() / n;
+    let sxx = x.iter().map(|xi| (xi - avg_x).powi(2)).sum::<f64>();
+    let syy = y.iter().map(|yi| (yi - avg_y).powi(2)).sum::<f64>();
+    let sxy = x
+        .iter()
+        .zip(y.iter())
+        .map(|(xi, yi)| (xi - avg_x) * (yi - avg_y))
+        .sum::<f64>();
+    let slope = sxy / sxx;
+    let intercept = avg_y - slope * avg_x;
+    let r_squared = sxy * sxy / (sxx * syy);
+    (intercept, slope, r_squared)
+}
+
+/// Two-way random effects, absolute agreement, single measurement intraclass correlation
+/// ICC(2,1) between two sets of paired measurements, with its 95% confidence interval.
+///
+/// Returns a tuple of (ICC, lower bound, upper bound).
+fn icc_2_1(x: &[f64], y: &[f64]) -> Result<(f64, f64, f64), Box<dyn Error>> {
+    let n = x.len() as f64;
+    let k = 2.0;
+    let grand = (x.iter().sum::<f64>() + y.iter().sum::<f64>()) / (n * k);
+    let avg_x = x.iter().sum::<f64>() / n;
+    let avg_y = y.iter().sum::<f64>() / n;
+    let row_avgs: Vec<_> = x.iter().zip(y.iter()).map(|(a, b)| (a + b) / k).collect();
+    let msr = k * row_avgs.iter().map(|r| (r - grand).powi(2)).sum::<f64>() / (n - 1.0);
+    let msc = n * ((avg_x - grand).powi(2) + (avg_y - grand).powi(2)) / (k - 1.0);
+    let sse = x
+        .iter()
+        .zip(y.iter())
+        .zip(row_avgs.iter())
+        .map(|((a, b), r)| (a - r - avg_x + grand).powi(2) + (b - r - avg_y + grand).powi(2))
+        .sum::<f64>();
+    let mse = sse / ((n - 1.0) * (k - 1.0));
+    let icc = (msr - mse) / (msr + (k - 1.0) * mse + k * (msc - mse) / n);
+
+    // Confidence interval according to McGraw and Wong (1996).
+    let a = k * icc / (n * (1.0 - icc));
+    let b = 1.0 + k * icc * (n - 1.0) / (n * (1.0 - icc));
+    let v = (a * msc + b * mse).powi(2)
+        / ((a * msc).powi(2) / (k - 1.0) + (b * mse).powi(2) / ((n - 1.0) * (k - 1.0)));
+    let (lower, upper) = if v.is_finite() && v > 0.0 {
+        let f_lower = FisherSnedecor::new(n - 1.0, v)?.inverse_cdf(0.975);
+        let f_upper = FisherSnedecor::new(v, n - 1.0)?.inverse_cdf(0.975);
+        let c = k * msc + (k * n - k - n) * mse;
+        (
+            n * (msr - f_lower * mse) / (f_lower * c + n * msr),
+            n * (f_upper * msr - mse) / (c + n * f_upper * msr),
+        )
+    } else {
+        (f64::NAN, f64::NAN)
+    };
+    Ok((icc, lower, upper))
+}
+
+/// Smallest and largest valid volume, None if there are no valid volumes.
+fn volume_range<'a, I: Iterator<Item = &'a Option<f64>>>(volumes: I) -> Option<(f64, f64)> {
+    volumes
+        .filter_map(|v| v.filter(|x| !x.is_nan()))
+        .fold(None, |range, x| match range {
+            None => Some((x, x)),
+            Some((min, max)) => Some((f64::min(min, x), f64::max(max, x))),
+        })
+}
+
+/// Patients with the largest volume shrinkage, see [Data::top_n_patients_by_shrinkage].
+///
+/// # Arguments
+///
+/// * `diffs` - (patient index, volume difference) pairs
+/// * `n` - maximum number of patients to return
+fn top_n_by_shrinkage(mut diffs: Vec<(usize, f64)>, n: usize) -> Vec<(String, f64)> {
+    diffs.sort_by(|a, b| b.1.total_cmp(&a.1));
+    diffs
+        .into_iter()
+        .take(n)
+        .map(|(i, d)| (i.to_string(), d))
+        .collect()
+}
+
+/// Empirical cumulative distribution of the volume differences of (patient index, volume
+/// difference) pairs.
+fn cdf_from_differences(diffs: Vec<(usize, f64)>) -> CDF {
+    CDF::new(diffs.into_iter().map(|(_, d)| d).collect())
 }
 
 /// Volumes for phase 1, 2 and 3 for a ROI.
@@ -123,6 +388,18 @@ impl Data {
         self.vol_phase_3.push(Some(v3) );
     }
 
+    /// Add volumes per phase which are known to be present.
+    ///
+    /// Volumes are discarded if one of the values is a NaN.
+    ///
+    /// # Arguments
+    ///
+    /// * `triplet` - volumes of phase I, II and III
+    pub fn add_vol_from_triplet(&mut self, triplet: (f64, f64, f64)) {
+        let (v1, v2, v3) = triplet;
+        self.add_vol(Some(v1), Some(v2), Some(v3));
+    }
+
     /// Clear all the volumes in the different phases.
     pub fn clear(&mut self) {
         self.vol_phase_1.clear();
@@ -130,9 +407,596 @@ impl Data {
         self.vol_phase_3.clear();
     }
 
+    /// Remove the patients whose volume in a phase is below a threshold, from all phases.
+    ///
+    /// Patients without a volume in the phase are kept. Returns the number of removed
+    /// patients.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase` - treatment phase [1, 2 or 3]
+    /// * `threshold` - minimum volume
+    pub fn remove_below_threshold(
+        &mut self,
+        phase: usize,
+        threshold: f64,
+    ) -> Result<usize, Box<dyn Error>> {
+        let removed: Vec<_> = self
+            .phase_volumes(phase)?
+            .iter()
+            .map(|v| v.is_some_and(|v| v < threshold))
+            .collect();
+        for (i, _) in removed.iter().enumerate().filter(|(_, r)| **r) {
+            info!(
+                "Removing patient [{}] from {}: phase {} volume below {}.",
+                i, self.roi_name, phase, threshold
+            );
+        }
+        for v in [&mut self.vol_phase_1, &mut self.vol_phase_2, &mut self.vol_phase_3] {
+            let mut keep = removed.iter().map(|r| !r);
+            v.retain(|_| keep.next().unwrap_or(true));
+        }
+        Ok(removed.iter().filter(|r| **r).count())
+    }
+
+    /// Apply exponential smoothing across the treatment phases of each patient.
+    ///
+    /// The volume of phase 1 is unchanged, the smoothed volume of the next phases is
+    /// `alpha * v_raw + (1 - alpha) * v_prev` where `v_prev` is the smoothed volume of the
+    /// previous phase.
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha` - smoothing factor in (0, 1], 1 leaves the volumes unchanged
+    pub fn exponential_smooth(&self, alpha: f64) -> Result<Data, Box<dyn Error>> {
+        if !(alpha > 0.0 && alpha <= 1.0) {
+            return Err(format!("Expected a smoothing factor in (0, 1] [{}].", alpha).into());
+        }
+        let smooth = |raw: &[Option<f64>], prev: &[Option<f64>]| -> Vec<Option<f64>> {
+            raw.iter()
+                .zip(prev.iter())
+                .map(|(v, p)| match (v, p) {
+                    (Some(v), Some(p)) => Some(alpha * v + (1.0 - alpha) * p),
+                    _ => None,
+                })
+                .collect()
+        };
+        let vol_phase_2 = smooth(&self.vol_phase_2, &self.vol_phase_1);
+        let vol_phase_3 = smooth(&self.vol_phase_3, &vol_phase_2);
+        Ok(Data {
+            roi_name: self.roi_name.clone(),
+            vol_phase_1: self.vol_phase_1.clone(),
+            vol_phase_2,
+            vol_phase_3,
+        })
+    }
+
+    /// Which phase volumes are present for each patient.
+    ///
+    /// Volumes added with [Data::add_vol] are always complete, use
+    /// [Records::phase_volume_missing_pattern] for the patterns of the raw records.
+    pub fn phase_volume_missing_pattern(&self) -> Vec<MissingPattern> {
+        let n = self
+            .vol_phase_1
+            .len()
+            .max(self.vol_phase_2.len())
+            .max(self.vol_phase_3.len());
+        let present = |v: &[Option<f64>], i: usize| {
+            v.get(i).is_some_and(|x| x.is_some_and(|x| !x.is_nan()))
+        };
+        (0..n)
+            .map(|i| MissingPattern {
+                has_phase_1: present(&self.vol_phase_1, i),
+                has_phase_2: present(&self.vol_phase_2, i),
+                has_phase_3: present(&self.vol_phase_3, i),
+            })
+            .collect()
+    }
+
+    /// Iterate over the treatment phases and their volumes.
+    pub fn phases(&self) -> PhaseIter<'_> {
+        PhaseIter {
+            data: self,
+            phase: 1,
+        }
+    }
+
+    /// Volumes of a treatment phase.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase` - treatment phase [1, 2 or 3]
+    fn phase_volumes(&self, phase: usize) -> Result<&Vec<Option<f64>>, Box<dyn Error>> {
+        match phase {
+            1 => Ok(&self.vol_phase_1),
+            2 => Ok(&self.vol_phase_2),
+            3 => Ok(&self.vol_phase_3),
+            _ => Err(format!("Unknown treatment phase [{}], expected 1, 2 or 3.", phase).into()),
+        }
+    }
+
+    /// Smallest and largest valid volume of a treatment phase.
+    ///
+    /// An error is returned if the phase has no valid volumes.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase` - treatment phase [1, 2 or 3]
+    pub fn phase_volume_range(&self, phase: usize) -> Result<(f64, f64), Box<dyn Error>> {
+        volume_range(self.phase_volumes(phase)?.iter())
+            .ok_or_else(|| {
+                format!("Empty dataset, no volumes in phase {} of {}.", phase, self.roi_name).into()
+            })
+    }
+
+    /// Returns true if a volume is within the range of the valid volumes of a treatment phase.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase` - treatment phase [1, 2 or 3]
+    /// * `value` - volume to check
+    pub fn phase_volume_contains(&self, phase: usize, value: f64) -> bool {
+        self.phase_volume_range(phase)
+            .is_ok_and(|(min, max)| min <= value && value <= max)
+    }
+
+    /// Smallest and largest valid volume across all treatment phases.
+    ///
+    /// An error is returned if there are no valid volumes.
+    pub fn range_all_phases(&self) -> Result<(f64, f64), Box<dyn Error>> {
+        let volumes = self
+            .vol_phase_1
+            .iter()
+            .chain(self.vol_phase_2.iter())
+            .chain(self.vol_phase_3.iter());
+        volume_range(volumes)
+            .ok_or_else(|| format!("Empty dataset, no volumes for {}.", self.roi_name).into())
+    }
+
+    /// Volume differences (start - end) per patient between two phases.
+    ///
+    /// Patients without a valid volume in one of the phases are discarded.
+    /// Returns a vector of (patient index, volume difference).
+    fn phase_differences(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<(usize, f64)>, Box<dyn Error>> {
+        let v1 = self.phase_volumes(start)?;
+        let v2 = self.phase_volumes(end)?;
+        Ok(v1
+            .iter()
+            .zip(v2.iter())
+            .enumerate()
+            .filter_map(|(i, (o1, o2))| match (o1, o2) {
+                (Some(x1), Some(x2)) => Some((i, x1 - x2)),
+                _ => None,
+            })
+            .filter(|(_, d)| !d.is_nan())
+            .collect())
+    }
+
+    /// Volumes (start, end) per patient for two phases.
+    ///
+    /// Patients without a valid volume in one of the phases are discarded.
+    fn phase_pairs(&self, start: usize, end: usize) -> Result<Vec<(f64, f64)>, Box<dyn Error>> {
+        let v1 = self.phase_volumes(start)?;
+        let v2 = self.phase_volumes(end)?;
+        Ok(v1
+            .iter()
+            .zip(v2.iter())
+            .filter_map(|(o1, o2)| match (o1, o2) {
+                (Some(x1), Some(x2)) if !x1.is_nan() && !x2.is_nan() => Some((*x1, *x2)),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Volumes of two phases for the patients with a valid volume in both phases.
+    ///
+    /// Returns a tuple of (start phase volumes, end phase volumes), both vectors have the same
+    /// length and contain no NaN values.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - treatment phase at the start [1, 2 or 3]
+    /// * `end` - treatment phase at the end [1, 2 or 3]
+    pub fn to_phase_pair_data(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Result<(Vec<f64>, Vec<f64>), Box<dyn Error>> {
+        Ok(self.phase_pairs(start, end)?.into_iter().unzip())
+    }
+
+    /// Ratio of the volume in phase 3 to the volume in phase 1 per patient.
+    ///
+    /// Patients with a phase 1 volume which is not strictly positive, or without a valid
+    /// phase 3 volume, are discarded.
+    pub fn shrinkage_ratios(&self) -> Vec<f64> {
+        self.vol_phase_1
+            .iter()
+            .zip(self.vol_phase_3.iter())
+            .filter_map(|(v1, v3)| match (v1, v3) {
+                (Some(v1), Some(v3)) if *v1 > 0.0 => Some(v3 / v1),
+                _ => None,
+            })
+            .filter(|r| r.is_finite())
+            .collect()
+    }
+
+    /// Geometric mean and geometric standard deviation factor of the shrinkage ratios, assuming
+    /// log-normally distributed ratios.
+    ///
+    /// Ratios which are not strictly positive are discarded.
+    pub fn shrinkage_ratio_stat(&self) -> ShrinkageRatioStat {
+        let log_ratios: Vec<_> = self
+            .shrinkage_ratios()
+            .into_iter()
+            .filter(|r| *r > 0.0)
+            .map(f64::ln)
+            .collect();
+        let (avg, std_dev, n) = avg_std_dev(&log_ratios);
+        ShrinkageRatioStat {
+            roi_name: self.roi_name.clone(),
+            geometric_mean: avg.exp(),
+            geometric_sd_factor: std_dev.exp(),
+            n,
+        }
+    }
+
+    /// Bland-Altman analysis of the agreement between the volumes of two phases.
+    ///
+    /// The limits of agreement are the mean difference (start - end) ± 1.96 times the standard
+    /// deviation of the differences.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - treatment phase at the start [1, 2 or 3]
+    /// * `end` - treatment phase at the end [1, 2 or 3]
+    pub fn bland_altman_analysis(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Result<BlandAltmanStat, Box<dyn Error>> {
+        let v: Vec<_> = self
+            .phase_differences(start, end)?
+            .into_iter()
+            .map(|(_, d)| d)
+            .collect();
+        if v.len() < 2 {
+            return Err(format!(
+                "Expected at least 2 patients for the Bland-Altman analysis [{}].",
+                v.len()
+            )
+                .into());
+        }
+        let (mean_bias, std_dev, n) = avg_std_dev(&v);
+        let upper_loa = mean_bias + 1.96 * std_dev;
+        let lower_loa = mean_bias - 1.96 * std_dev;
+        let within = v.iter().filter(|d| **d >= lower_loa && **d <= upper_loa).count();
+        Ok(BlandAltmanStat {
+            roi_name: self.roi_name.clone(),
+            phase_start: start as i32,
+            phase_end: end as i32,
+            mean_bias,
+            upper_loa,
+            lower_loa,
+            within_loa_fraction: within as f64 / n as f64,
+        })
+    }
+
+    /// Intraclass correlation ICC(2,1) between each pair of treatment phases.
+    ///
+    /// Element `[i][j]` contains the correlation between phase `i + 1` and `j + 1`, the
+    /// diagonal is 1.
+    pub fn pairwise_icc(&self) -> Result<IccMatrix, Box<dyn Error>> {
+        let mut m = IccMatrix {
+            values: [[1.0; 3]; 3],
+            ci_lower: [[1.0; 3]; 3],
+            ci_upper: [[1.0; 3]; 3],
+        };
+        for i in 0..3 {
+            for j in (i + 1)..3 {
+                let (x, y) = self.to_phase_pair_data(i + 1, j + 1)?;
+                if x.len() < 2 {
+                    return Err(format!(
+                        "Expected at least 2 patients for the ICC between phase {} and {} [{}].",
+                        i + 1,
+                        j + 1,
+                        x.len()
+                    )
+                        .into());
+                }
+                let (icc, lower, upper) = icc_2_1(&x, &y)?;
+                m.values[i][j] = icc;
+                m.values[j][i] = icc;
+                m.ci_lower[i][j] = lower;
+                m.ci_lower[j][i] = lower;
+                m.ci_upper[i][j] = upper;
+                m.ci_upper[j][i] = upper;
+            }
+        }
+        Ok(m)
+    }
+
+    /// Volume differences (start - end) between two phases, without the patient indices.
+    fn phase_difference_values(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<f64>, Box<dyn Error>> {
+        Ok(self
+            .phase_differences(start, end)?
+            .into_iter()
+            .map(|(_, d)| d)
+            .collect())
+    }
+
+    /// Adjusted Fisher-Pearson skewness of the volume differences (start - end) between two
+    /// phases.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - treatment phase at the start [1, 2 or 3]
+    /// * `end` - treatment phase at the end [1, 2 or 3]
+    pub fn skewness_of_differences(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Result<f64, Box<dyn Error>> {
+        let v = self.phase_difference_values(start, end)?;
+        if v.len() < 3 {
+            return Err(
+                format!("Expected at least 3 patients for the skewness [{}].", v.len()).into()
+            );
+        }
+        let (avg, std_dev, n) = avg_std_dev(&v);
+        let n = n as f64;
+        let sum = v.iter().map(|d| ((d - avg) / std_dev).powi(3)).sum::<f64>();
+        Ok(n / ((n - 1.0) * (n - 2.0)) * sum)
+    }
+
+    /// Excess kurtosis of the volume differences (start - end) between two phases, using the
+    /// sample estimator
+    /// `n(n+1) / ((n-1)(n-2)(n-3)) * sum((d - avg)^4 / s^4) - 3(n-1)^2 / ((n-2)(n-3))`.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - treatment phase at the start [1, 2 or 3]
+    /// * `end` - treatment phase at the end [1, 2 or 3]
+    pub fn kurtosis_of_differences(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Result<f64, Box<dyn Error>> {
+        let v = self.phase_difference_values(start, end)?;
+        if v.len() < 4 {
+            return Err(
+                format!("Expected at least 4 patients for the kurtosis [{}].", v.len()).into()
+            );
+        }
+        let (avg, std_dev, n) = avg_std_dev(&v);
+        let n = n as f64;
+        let sum = v.iter().map(|d| ((d - avg) / std_dev).powi(4)).sum::<f64>();
+        Ok(n * (n + 1.0) / ((n - 1.0) * (n - 2.0) * (n - 3.0)) * sum
+            - 3.0 * (n - 1.0).powi(2) / ((n - 2.0) * (n - 3.0)))
+    }
+
+    /// Check the shape of the distribution of the volume differences (start - end) between two
+    /// phases to choose between a t-test and a Wilcoxon signed-rank test.
+    ///
+    /// The Wilcoxon test is recommended if the absolute skewness exceeds 1 or the excess
+    /// kurtosis exceeds 3.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - treatment phase at the start [1, 2 or 3]
+    /// * `end` - treatment phase at the end [1, 2 or 3]
+    pub fn normality_check(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Result<NormalityCheck, Box<dyn Error>> {
+        let skewness = self.skewness_of_differences(start, end)?;
+        let kurtosis = self.kurtosis_of_differences(start, end)?;
+        let recommended_test = if skewness.abs() > 1.0 || kurtosis > 3.0 {
+            RecommendedTest::Wilcoxon
+        } else {
+            RecommendedTest::TTest
+        };
+        Ok(NormalityCheck {
+            skewness,
+            kurtosis,
+            recommended_test,
+        })
+    }
+
+    /// Cox-Stuart test for a monotone trend of the volumes between phase 1 and phase 3.
+    ///
+    /// Each patient contributes the sign of `v3 - v1`, ties are discarded. The number of
+    /// positive signs is compared to its expected value under the null hypothesis of no trend
+    /// using the normal approximation of the sign test.
+    pub fn cox_stuart_test(&self) -> Result<CoxStuartResult, Box<dyn Error>> {
+        let pairs = self.phase_pairs(1, 3)?;
+        let n_plus = pairs.iter().filter(|(v1, v3)| v3 > v1).count();
+        let n_minus = pairs.iter().filter(|(v1, v3)| v3 < v1).count();
+        let n = n_plus + n_minus;
+        if n == 0 {
+            return Err("Expected at least one patient with a volume change for the \
+                Cox-Stuart test.".into());
+        }
+        let z_statistic = (n_plus as f64 - n as f64 / 2.0) / (n as f64 / 4.0).sqrt();
+        let p_value = 2.0 * Normal::standard().sf(z_statistic.abs());
+        Ok(CoxStuartResult {
+            n_plus,
+            n_minus,
+            z_statistic,
+            p_value,
+        })
+    }
+
+    /// Breusch-Pagan test for heteroscedasticity of the volume differences (start - end) with
+    /// respect to the volume at the start phase.
+    ///
+    /// The volume differences are regressed on the start volumes, after which the squared
+    /// residuals are regressed on the start volumes. The test statistic `n * R^2` of the second
+    /// regression follows a chi-squared distribution with one degree of freedom.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - treatment phase at the start [1, 2 or 3]
+    /// * `end` - treatment phase at the end [1, 2 or 3]
+    pub fn heteroscedasticity_test(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Result<HeteroscedasticityResult, Box<dyn Error>> {
+        let pairs = self.phase_pairs(start, end)?;
+        if pairs.len() < 3 {
+            return Err(format!(
+                "Expected at least 3 patients for the Breusch-Pagan test [{}].",
+                pairs.len()
+            )
+                .into());
+        }
+        let x: Vec<_> = pairs.iter().map(|(v1, _)| *v1).collect();
+        let y: Vec<_> = pairs.iter().map(|(v1, v2)| v1 - v2).collect();
+        let (intercept, slope, _) = linear_regression(&x, &y);
+        let residuals: Vec<_> = x
+            .iter()
+            .zip(y.iter())
+            .map(|(xi, yi)| (yi - intercept - slope * xi).powi(2))
+            .collect();
+        let (_, _, r_squared) = linear_regression(&x, &residuals);
+        let statistic = pairs.len() as f64 * r_squared;
+        if !statistic.is_finite() {
+            return Err("Breusch-Pagan test statistic is not finite, the start volumes or \
+                residuals have no variance.".into());
+        }
+        let df = 1;
+        let p_value = ChiSquared::new(df as f64)?.sf(statistic);
+        Ok(HeteroscedasticityResult {
+            statistic,
+            df,
+            p_value,
+        })
+    }
+
+    /// Coefficient of variation (standard deviation / average) of the volumes in phase 1, 2
+    /// and 3 per patient.
+    ///
+    /// Patients without a valid volume in one of the phases, or for which the coefficient of
+    /// variation is not finite, are discarded.
+    pub fn intra_patient_coefficient_of_variation(&self) -> Vec<f64> {
+        self.vol_phase_1
+            .iter()
+            .zip(self.vol_phase_2.iter())
+            .zip(self.vol_phase_3.iter())
+            .filter_map(|((v1, v2), v3)| match (v1, v2, v3) {
+                (Some(v1), Some(v2), Some(v3)) => {
+                    let (avg, std_dev, _) = avg_std_dev(&[*v1, *v2, *v3]);
+                    Some(std_dev / avg)
+                }
+                _ => None,
+            })
+            .filter(|cv| cv.is_finite())
+            .collect()
+    }
+
+    /// Average and standard deviation of the intra-patient coefficients of variation.
+    pub fn intra_patient_cv_stat(&self) -> CvStat {
+        let (avg, std_dev, n) = avg_std_dev(&self.intra_patient_coefficient_of_variation());
+        CvStat {
+            roi_name: self.roi_name.clone(),
+            avg,
+            std_dev,
+            n,
+        }
+    }
+
+    /// Statistics of a subset of the volume differences between two phases.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - treatment phase at the start [1, 2 or 3]
+    /// * `end` - treatment phase at the end [1, 2 or 3]
+    /// * `diffs` - (patient index, volume difference) of the patients to include
+    fn stat_from_differences(
+        &self,
+        start: usize,
+        end: usize,
+        diffs: &[(usize, f64)],
+    ) -> Result<Stat, Box<dyn Error>> {
+        let vol_start = self.phase_volumes(start)?;
+        let v: Vec<_> = diffs.iter().map(|(_, d)| *d).collect();
+        let avg_vol = diffs
+            .iter()
+            .map(|(i, _)| vol_start[*i].unwrap_or(f64::NAN))
+            .sum::<f64>() / diffs.len() as f64;
+        let (avg, std_dev, n) = avg_std_dev(&v);
+        let (avg_pct, std_dev_pct, n_pct) = avg_std_dev_pct(diffs.iter().map(|(i, d)| {
+            let v_start = vol_start[*i].unwrap_or(f64::NAN);
+            (v_start, v_start - d)
+        }));
+        Ok(Stat {
+            roi_name: self.roi_name.clone(),
+            avg_vol_phase_start: avg_vol,
+            phase_start: start as i32,
+            phase_end: end as i32,
+            avg,
+            std_dev,
+            n,
+            avg_pct,
+            std_dev_pct,
+            n_pct,
+        })
+    }
+
+    /// Patients with the largest volume shrinkage between two phases.
+    ///
+    /// The shrinkage is computed as the volume at the start phase minus the volume at the end
+    /// phase, a positive value means the volume decreased. Patient IDs are not tracked by `Data`,
+    /// so patients are identified by their index (as a string) in the order they were added.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - maximum number of patients to return, all patients are returned if `n` exceeds
+    ///   the number of patients
+    /// * `start` - treatment phase at the start [1, 2 or 3]
+    /// * `end` - treatment phase at the end [1, 2 or 3]
+    ///
+    /// Returns a vector of (patient, shrinkage) sorted from the largest to the smallest
+    /// shrinkage.
+    pub fn top_n_patients_by_shrinkage(
+        &self,
+        n: usize,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<(String, f64)>, Box<dyn Error>> {
+        Ok(top_n_by_shrinkage(self.phase_differences(start, end)?, n))
+    }
+
+    /// Empirical cumulative distribution of the volume differences (start - end) between two
+    /// phases.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - treatment phase at the start [1, 2 or 3]
+    /// * `end` - treatment phase at the end [1, 2 or 3]
+    pub fn cumulative_volume_change_distribution(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Result<CDF, Box<dyn Error>> {
+        Ok(cdf_from_differences(self.phase_differences(start, end)?))
+    }
+
     pub fn phase_1_to_2_stat(&self) -> Result<Stat, Box<dyn Error>> {
         let avg_vol = self.vol_phase_1.iter().map(|x| x.unwrap_or(f64::NAN)).sum::<f64>() / self.vol_phase_1.len() as f64;
         let (avg, std_dev, n) = avg_std_dev_from_vectors(&self.vol_phase_1, &self.vol_phase_2)?;
+        let (avg_pct, std_dev_pct, n_pct) =
+            avg_std_dev_pct_from_vectors(&self.vol_phase_1, &self.vol_phase_2);
         Ok(Stat {
             roi_name: self.roi_name.clone(),
             avg_vol_phase_start: avg_vol,
@@ -141,11 +1005,16 @@ impl Data {
             avg,
             std_dev,
             n,
+            avg_pct,
+            std_dev_pct,
+            n_pct,
         })
     }
     pub fn phase_2_to_3_stat(&self) -> Result<Stat, Box<dyn Error>> {
         let avg_vol = self.vol_phase_2.iter().map(|x| x.unwrap_or(f64::NAN)).sum::<f64>() / self.vol_phase_1.len() as f64;
         let (avg, std_dev, n) = avg_std_dev_from_vectors(&self.vol_phase_2, &self.vol_phase_3)?;
+        let (avg_pct, std_dev_pct, n_pct) =
+            avg_std_dev_pct_from_vectors(&self.vol_phase_2, &self.vol_phase_3);
         Ok(Stat {
             roi_name: self.roi_name.clone(),
             avg_vol_phase_start: avg_vol,
@@ -154,37 +1023,1107 @@ impl Data {
             avg,
             std_dev,
             n,
+            avg_pct,
+            std_dev_pct,
+            n_pct,
         })
     }
 }
 
-pub fn read_csv(filename: &str) -> Result<Vec<Record>, Box<dyn Error>> {
-    let mut rdr = csv::ReaderBuilder::new()
-        .delimiter(b';')
-        .has_headers(true)
-        .from_path(filename)?;
-    let mut v = vec![];
-    for result in rdr.deserialize() {
-        let record = result?;
-        v.push(record);
+/// Create a wide-format DataFrame with a `Patient ID` column and one column (`<ROI>_phase_<N>`)
+/// per phase per ROI.
+///
+/// Row `i` contains the volumes of the `i`-th record, missing and NaN volumes are nulls.
+///
+/// # Arguments
+///
+/// * `records` - volumes per patient
+#[cfg(feature = "polars")]
+pub fn to_polars_dataframe(
+    records: &Records,
+) -> polars::prelude::PolarsResult<polars::frame::DataFrame> {
+    use polars::prelude::{Column, DataFrame};
+    let patient_ids: Vec<_> = records.iter().map(|r| r.patient_id.clone()).collect();
+    let mut columns = vec![Column::new("Patient ID".into(), patient_ids)];
+    let mut map = records.to_transposed_map();
+    for roi in Record::ROI_NAMES {
+        for phase in 1..=3 {
+            let name = format!("{}_phase_{}", roi, phase);
+            let v: Vec<_> = map
+                .remove(&name)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|x| x.filter(|x| !x.is_nan()))
+                .collect();
+            columns.push(Column::new(name.into(), v));
+        }
     }
-    Ok(v)
+    DataFrame::new(records.len(), columns)
 }
 
-pub fn records_to_data(records: &Vec<Record>) -> Vec<Data> {
-    let mut gtv = Data::new("GTV");
-    let mut gtv_n = Data::new("GTV_N");
-    let mut ptv_dp = Data::new("PTV_DP");
-    for record in records {
-        gtv.add_vol(
-            record.gtv_phase_i,
-            record.gtv_phase_ii,
-            record.gtv_phase_iii,
-        );
-        gtv_n.add_vol(
-            record.gtv_n_phase_i,
-            record.gtv_n_phase_ii,
-            record.gtv_n_phase_iii,
+/// Volumes for phase 1, 2 and 3 for a ROI, where missing volumes are stored as NaN.
+///
+/// Compared to [Data], this halves the memory used per volume. It is a storage-focused subset
+/// of [Data]: only the construction, phase iteration, shrinkage ranking, ECDF and phase
+/// statistics methods are provided. Convert it with [Data::from] to run the other analyses.
+#[derive(Clone, Debug, Default)]
+pub struct EfficientData {
+    pub roi_name: String,
+    vol_phase_1: Vec<f64>,
+    vol_phase_2: Vec<f64>,
+    vol_phase_3: Vec<f64>,
+}
+
+impl EfficientData {
+    /// Create a new ROI with a name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - ROI name
+    pub fn new<S: AsRef<str>>(name: S) -> Self {
+        Self {
+            roi_name: name.as_ref().to_string(),
+            vol_phase_1: Default::default(),
+            vol_phase_2: Default::default(),
+            vol_phase_3: Default::default(),
+        }
+    }
+
+    /// Add volumes per phase.
+    ///
+    /// Volumes are discarded if one of the input arguments is None or it's value is a NaN.
+    ///
+    /// # Arguments
+    ///
+    /// * `v1` - volume phase I
+    /// * `v2` - volume phase II
+    /// * `v3` - volume phase III
+    pub fn add_vol(&mut self, v1: Option<f64>, v2: Option<f64>, v3: Option<f64>) {
+        let v1 = v1.unwrap_or(f64::NAN);
+        let v2 = v2.unwrap_or(f64::NAN);
+        let v3 = v3.unwrap_or(f64::NAN);
+        if v1.is_nan() || v2.is_nan() || v3.is_nan() {
+            return;
+        }
+        self.vol_phase_1.push(v1);
+        self.vol_phase_2.push(v2);
+        self.vol_phase_3.push(v3);
+    }
+
+    /// Clear all the volumes in the different phases.
+    pub fn clear(&mut self) {
+        self.vol_phase_1.clear();
+        self.vol_phase_2.clear();
+        self.vol_phase_3.clear();
+    }
+
+    /// Iterate over the treatment phases and their volumes, missing volumes are NaN.
+    pub fn phases(&self) -> EfficientPhaseIter<'_> {
+        EfficientPhaseIter {
+            data: self,
+            phase: 1,
+        }
+    }
+
+    /// Volumes of a treatment phase.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase` - treatment phase [1, 2 or 3]
+    fn phase_volumes(&self, phase: usize) -> Result<&Vec<f64>, Box<dyn Error>> {
+        match phase {
+            1 => Ok(&self.vol_phase_1),
+            2 => Ok(&self.vol_phase_2),
+            3 => Ok(&self.vol_phase_3),
+            _ => Err(format!("Unknown treatment phase [{}], expected 1, 2 or 3.", phase).into()),
+        }
+    }
+
+    /// Volume differences (start - end) per patient between two phases.
+    ///
+    /// Patients without a valid volume in one of the phases are discarded.
+    /// Returns a vector of (patient index, volume difference).
+    fn phase_differences(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<(usize, f64)>, Box<dyn Error>> {
+        let v1 = self.phase_volumes(start)?;
+        let v2 = self.phase_volumes(end)?;
+        Ok(v1
+            .iter()
+            .zip(v2.iter())
+            .map(|(x1, x2)| x1 - x2)
+            .enumerate()
+            .filter(|(_, d)| !d.is_nan())
+            .collect())
+    }
+
+    /// Patients with the largest volume shrinkage between two phases.
+    ///
+    /// See [Data::top_n_patients_by_shrinkage].
+    pub fn top_n_patients_by_shrinkage(
+        &self,
+        n: usize,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<(String, f64)>, Box<dyn Error>> {
+        Ok(top_n_by_shrinkage(self.phase_differences(start, end)?, n))
+    }
+
+    /// Empirical cumulative distribution of the volume differences (start - end) between two
+    /// phases.
+    ///
+    /// See [Data::cumulative_volume_change_distribution].
+    pub fn cumulative_volume_change_distribution(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Result<CDF, Box<dyn Error>> {
+        Ok(cdf_from_differences(self.phase_differences(start, end)?))
+    }
+
+    /// Statistics of the volume changes between two phases.
+    fn phase_stat(&self, start: usize, end: usize) -> Result<Stat, Box<dyn Error>> {
+        let vol_start = self.phase_volumes(start)?;
+        let avg_vol = vol_start.iter().sum::<f64>() / vol_start.len() as f64;
+        let v: Vec<_> = self
+            .phase_differences(start, end)?
+            .into_iter()
+            .map(|(_, d)| d)
+            .collect();
+        let (avg, std_dev, n) = avg_std_dev(&v);
+        let vol_end = self.phase_volumes(end)?;
+        let (avg_pct, std_dev_pct, n_pct) =
+            avg_std_dev_pct(vol_start.iter().copied().zip(vol_end.iter().copied()));
+        Ok(Stat {
+            roi_name: self.roi_name.clone(),
+            avg_vol_phase_start: avg_vol,
+            phase_start: start as i32,
+            phase_end: end as i32,
+            avg,
+            std_dev,
+            n,
+            avg_pct,
+            std_dev_pct,
+            n_pct,
+        })
+    }
+
+    pub fn phase_1_to_2_stat(&self) -> Result<Stat, Box<dyn Error>> {
+        self.phase_stat(1, 2)
+    }
+
+    pub fn phase_2_to_3_stat(&self) -> Result<Stat, Box<dyn Error>> {
+        self.phase_stat(2, 3)
+    }
+}
+
+impl From<Data> for EfficientData {
+    fn from(data: Data) -> Self {
+        let materialise = |v: Vec<Option<f64>>| -> Vec<f64> {
+            v.into_iter().map(|x| x.unwrap_or(f64::NAN)).collect()
+        };
+        Self {
+            roi_name: data.roi_name,
+            vol_phase_1: materialise(data.vol_phase_1),
+            vol_phase_2: materialise(data.vol_phase_2),
+            vol_phase_3: materialise(data.vol_phase_3),
+        }
+    }
+}
+
+impl From<EfficientData> for Data {
+    fn from(data: EfficientData) -> Self {
+        let optional = |v: Vec<f64>| -> Vec<Option<f64>> {
+            v.into_iter().map(|x| Some(x).filter(|x| !x.is_nan())).collect()
+        };
+        Self {
+            roi_name: data.roi_name,
+            vol_phase_1: optional(data.vol_phase_1),
+            vol_phase_2: optional(data.vol_phase_2),
+            vol_phase_3: optional(data.vol_phase_3),
+        }
+    }
+}
+
+/// Presence of the volumes of a patient in each treatment phase.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MissingPattern {
+    pub has_phase_1: bool,
+    pub has_phase_2: bool,
+    pub has_phase_3: bool,
+}
+
+/// Count how often each missing pattern occurs.
+///
+/// # Arguments
+///
+/// * `patterns` - missing pattern per patient
+pub fn pattern_frequency_table(patterns: &[MissingPattern]) -> HashMap<MissingPattern, usize> {
+    let mut m = HashMap::new();
+    for pattern in patterns {
+        *m.entry(*pattern).or_insert(0) += 1;
+    }
+    m
+}
+
+/// Completeness of the volumes of all ROIs and phases.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DataQualityReport {
+    /// Number of patients
+    pub n_patients: usize,
+    /// Number of patients with a volume, per column name (e.g. `GTV_phase_1`)
+    pub present_volume_counts: BTreeMap<String, usize>,
+    /// Volumes which are likely reported in the wrong unit, see
+    /// [Records::detect_unit_inconsistencies]
+    #[serde(default)]
+    pub unit_inconsistencies: Vec<UnitInconsistency>,
+}
+
+impl DataQualityReport {
+    /// Number of volumes present.
+    pub fn present_volume_count(&self) -> usize {
+        self.present_volume_counts.values().sum()
+    }
+
+    /// Number of volumes if all patients had a volume for each ROI and phase.
+    pub fn total_possible_volume_count(&self) -> usize {
+        self.n_patients * self.present_volume_counts.len()
+    }
+
+    /// Fraction [0, 1] of the volumes present, 0 if there are no patients.
+    pub fn summary_score(&self) -> f64 {
+        let total = self.total_possible_volume_count();
+        if total == 0 {
+            return 0.0;
+        }
+        self.present_volume_count() as f64 / total as f64
+    }
+
+    /// Rating of the summary score: Excellent (> 0.9), Good (0.7 - 0.9) or Poor (< 0.7).
+    pub fn quality_rating(&self) -> &'static str {
+        let score = self.summary_score();
+        if score > 0.9 {
+            "Excellent"
+        } else if score >= 0.7 {
+            "Good"
+        } else {
+            "Poor"
+        }
+    }
+}
+
+impl fmt::Display for DataQualityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} volumes present, score {:.3} ({})",
+            self.present_volume_count(),
+            self.total_possible_volume_count(),
+            self.summary_score(),
+            self.quality_rating()
+        )?;
+        for (column, count) in &self.present_volume_counts {
+            write!(f, "\n{}/{} patients have {} data", count, self.n_patients, column)?;
+        }
+        write!(
+            f,
+            "\n{} volumes likely reported in the wrong unit",
+            self.unit_inconsistencies.len()
+        )?;
+        for u in &self.unit_inconsistencies {
+            write!(
+                f,
+                "\nPatient [{}] {} phase {}: volume {} outside [{}, {}]",
+                u.patient_id, u.roi, u.phase, u.volume, u.expected_range.0, u.expected_range.1
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Which records to keep when multiple records have the same patient ID.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Keep the first record
+    #[default]
+    KeepFirst,
+    /// Keep the last record
+    KeepLast,
+    /// Keep all records, a warning is logged if their volumes differ
+    KeepAll,
+}
+
+/// Volume differences of a patient (patient ID, phase 1 - phase 2, phase 2 - phase 3).
+pub type PatientPhaseDeltas = (String, f64, f64);
+
+/// Collection of records read from a CSV file.
+#[derive(Clone, Debug, Default)]
+pub struct Records(pub Vec<Record>);
+
+impl Records {
+    /// Parse the records from a CSV string.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - CSV content, including the header
+    /// * `delimiter` - delimiter used between the values
+    pub fn from_csv_str(content: &str, delimiter: u8) -> Result<Records, Box<dyn Error>> {
+        let rdr = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(true)
+            .from_reader(content.as_bytes());
+        Ok(Records(deserialize_records(rdr)?))
+    }
+
+    /// Parse the records from a semicolon delimited CSV string.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - CSV content, including the header
+    pub fn from_csv_str_semicolon(content: &str) -> Result<Records, Box<dyn Error>> {
+        Self::from_csv_str(content, b';')
+    }
+
+    /// Check that all the required columns are present in the CSV headers.
+    ///
+    /// # Arguments
+    ///
+    /// * `headers` - headers of the CSV file
+    /// * `required_headers` - columns that must be present
+    ///
+    /// Returns an error for the first required column that is not present.
+    pub fn validate_column_presence(
+        headers: &[&str],
+        required_headers: &[&str],
+    ) -> Result<(), Box<dyn Error>> {
+        match required_headers.iter().find(|r| !headers.contains(r)) {
+            Some(missing) => Err(format!("Missing column [{}] in the CSV file.", missing).into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Detect volumes which are likely reported in the wrong unit (e.g. mm³ instead of cm³).
+    ///
+    /// A volume is flagged if it's larger than 10 times the median volume of the ROI across
+    /// all records and phases.
+    pub fn detect_unit_inconsistencies(&self) -> Vec<UnitInconsistency> {
+        self.detect_unit_inconsistencies_with_multiplier(10.0)
+    }
+
+    /// Detect volumes which are likely reported in the wrong unit (e.g. mm³ instead of cm³).
+    ///
+    /// # Arguments
+    ///
+    /// * `multiplier` - a volume is flagged if it's larger than `multiplier` times the median
+    ///   of the strictly positive volumes of the ROI across all records and phases
+    ///
+    /// Zero volumes (e.g. patients without nodal volume) are excluded from the median, a ROI
+    /// without positive volumes is skipped.
+    pub fn detect_unit_inconsistencies_with_multiplier(
+        &self,
+        multiplier: f64,
+    ) -> Vec<UnitInconsistency> {
+        let mut v = vec![];
+        for roi in Record::ROI_NAMES {
+            let volumes: Vec<_> = self
+                .iter()
+                .flat_map(|r| (1..=3).filter_map(move |phase| r.roi_volume(roi, phase)))
+                .filter(|volume| *volume > 0.0)
+                .collect();
+            // The median of the strictly positive volumes is positive, if there are any.
+            if volumes.is_empty() {
+                continue;
+            }
+            let med = median(&volumes);
+            let expected_range = (0.0, multiplier * med);
+            for record in self.iter() {
+                for phase in 1..=3 {
+                    match record.roi_volume(roi, phase) {
+                        Some(volume) if volume > expected_range.1 => {
+                            v.push(UnitInconsistency {
+                                patient_id: record.patient_id.clone(),
+                                roi: roi.to_string(),
+                                phase,
+                                volume,
+                                expected_range,
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        v
+    }
+
+    /// Sort the records by the volume of a ROI in a treatment phase, from large to small.
+    ///
+    /// Records without a volume are placed at the end.
+    ///
+    /// # Arguments
+    ///
+    /// * `roi` - ROI name [GTV, GTV_N or PTV_DP]
+    /// * `phase` - treatment phase [1, 2 or 3]
+    pub fn sort_by_volume_descending(
+        &self,
+        roi: &str,
+        phase: usize,
+    ) -> Result<Records, Box<dyn Error>> {
+        validate_roi_phase(roi, phase)?;
+        let mut v = self.0.clone();
+        v.sort_by(|a, b| {
+            let va = a.roi_volume(roi, phase).filter(|x| !x.is_nan());
+            let vb = b.roi_volume(roi, phase).filter(|x| !x.is_nan());
+            match (va, vb) {
+                (Some(x), Some(y)) => y.total_cmp(&x),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+        });
+        Ok(Records(v))
+    }
+
+    /// Volume differences per patient between the consecutive treatment phases of a ROI.
+    ///
+    /// Only patients with a volume in all three phases are included.
+    /// Returns a vector of (patient ID, phase 1 - phase 2, phase 2 - phase 3).
+    ///
+    /// # Arguments
+    ///
+    /// * `roi` - ROI name [GTV, GTV_N or PTV_DP]
+    pub fn compute_phase_deltas_per_patient(
+        &self,
+        roi: &str,
+    ) -> Result<Vec<PatientPhaseDeltas>, Box<dyn Error>> {
+        validate_roi_phase(roi, 1)?;
+        Ok(self
+            .iter()
+            .filter_map(|r| {
+                let v1 = r.roi_volume(roi, 1)?;
+                let v2 = r.roi_volume(roi, 2)?;
+                let v3 = r.roi_volume(roi, 3)?;
+                Some((r.patient_id.clone(), v1 - v2, v2 - v3))
+            })
+            .filter(|(_, d12, d23)| !d12.is_nan() && !d23.is_nan())
+            .collect())
+    }
+
+    /// Move all the records of `other` into `self`.
+    ///
+    /// Returns the number of records of `other` which were added.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - records to add
+    /// * `strict` - remove records with a duplicate patient ID after merging, keeping the first
+    ///   occurrence
+    pub fn append(&mut self, mut other: Records, strict: bool) -> usize {
+        if strict {
+            // Duplicates already in `self` are removed first, so only records of `other` are
+            // removed after merging.
+            self.dedup_by_patient_id();
+        }
+        let n = self.0.len();
+        self.0.append(&mut other.0);
+        if strict {
+            self.dedup_by_patient_id();
+        }
+        self.0.len() - n
+    }
+
+    /// Remove records with a duplicate patient ID, keeping the first occurrence.
+    ///
+    /// Returns the number of records removed.
+    pub fn dedup_by_patient_id(&mut self) -> usize {
+        let n = self.0.len();
+        self.deduplicate_by_patient_id(DuplicatePolicy::KeepFirst);
+        n - self.0.len()
+    }
+
+    /// Handle records with duplicate patient IDs.
+    ///
+    /// Returns the number of patient IDs occurring more than once.
+    ///
+    /// # Arguments
+    ///
+    /// * `keep` - which of the duplicate records are kept
+    pub fn deduplicate_by_patient_id(&mut self, keep: DuplicatePolicy) -> usize {
+        let mut groups: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, r) in self.0.iter().enumerate() {
+            groups.entry(r.patient_id.as_str()).or_default().push(i);
+        }
+        let duplicates = groups.values().filter(|g| g.len() > 1).count();
+        let kept: HashSet<usize> = match keep {
+            DuplicatePolicy::KeepFirst => groups.values().map(|g| g[0]).collect(),
+            DuplicatePolicy::KeepLast => groups.values().map(|g| g[g.len() - 1]).collect(),
+            DuplicatePolicy::KeepAll => {
+                for (id, g) in groups.iter().filter(|(_, g)| g.len() > 1) {
+                    let first = &self.0[g[0]];
+                    if g[1..].iter().any(|i| !self.0[*i].has_same_volumes(first)) {
+                        warn!("Duplicate records of patient [{}] have different volumes.", id);
+                    }
+                }
+                return duplicates;
+            }
+        };
+        let mut i = 0;
+        self.0.retain(|_| {
+            i += 1;
+            kept.contains(&(i - 1))
+        });
+        duplicates
+    }
+
+    /// Which phase volumes of a ROI are present for each record.
+    ///
+    /// # Arguments
+    ///
+    /// * `roi` - ROI name [GTV, GTV_N or PTV_DP]
+    pub fn phase_volume_missing_pattern(
+        &self,
+        roi: &str,
+    ) -> Result<Vec<MissingPattern>, Box<dyn Error>> {
+        validate_roi_phase(roi, 1)?;
+        let present = |r: &Record, phase| r.roi_volume(roi, phase).is_some_and(|x| !x.is_nan());
+        Ok(self
+            .iter()
+            .map(|r| MissingPattern {
+                has_phase_1: present(r, 1),
+                has_phase_2: present(r, 2),
+                has_phase_3: present(r, 3),
+            })
+            .collect())
+    }
+
+    /// Group the records by the ROIs for which the volumes of all three phases are present.
+    ///
+    /// Returns a map with the ROI name as key and the records with complete volumes for that
+    /// ROI as value. A record can be part of multiple groups.
+    pub fn group_by_roi_completeness(&self) -> HashMap<String, Records> {
+        Record::ROI_NAMES
+            .iter()
+            .map(|roi| {
+                let v: Vec<_> = self
+                    .iter()
+                    .filter(|r| r.has_complete_volumes(roi))
+                    .cloned()
+                    .collect();
+                (roi.to_string(), Records(v))
+            })
+            .collect()
+    }
+
+    /// Records for which the volumes of all ROIs in all three phases are present.
+    pub fn complete_cases_for_all_rois(&self) -> Records {
+        let v: Vec<_> = self
+            .iter()
+            .filter(|r| Record::ROI_NAMES.iter().all(|roi| r.has_complete_volumes(roi)))
+            .cloned()
+            .collect();
+        Records(v)
+    }
+
+    /// Completeness of the volumes in the records and the volumes which are likely reported in
+    /// the wrong unit.
+    pub fn data_quality_report(&self) -> DataQualityReport {
+        let mut present_volume_counts = BTreeMap::new();
+        for roi in Record::ROI_NAMES {
+            for phase in 1..=3 {
+                present_volume_counts.insert(
+                    format!("{}_phase_{}", roi, phase),
+                    self.present_volume_count(roi, phase),
+                );
+            }
+        }
+        DataQualityReport {
+            n_patients: self.patient_count(),
+            present_volume_counts,
+            unit_inconsistencies: self.detect_unit_inconsistencies(),
+        }
+    }
+
+    /// Column oriented view of the volumes.
+    ///
+    /// Maps each volume column name (e.g. `GTV_phase_1`) on the volumes of that column, one per
+    /// record and in record order.
+    pub fn to_transposed_map(&self) -> HashMap<String, Vec<Option<f64>>> {
+        let mut map = HashMap::new();
+        for roi in Record::ROI_NAMES {
+            for phase in 1..=3 {
+                map.insert(
+                    format!("{}_phase_{}", roi, phase),
+                    self.iter().map(|r| r.roi_volume(roi, phase)).collect(),
+                );
+            }
+        }
+        map
+    }
+
+    /// Number of patients.
+    pub fn patient_count(&self) -> usize {
+        self.len()
+    }
+
+    /// Number of records with a volume for a ROI in a treatment phase.
+    ///
+    /// # Arguments
+    ///
+    /// * `roi` - ROI name [GTV, GTV_N or PTV_DP]
+    /// * `phase` - treatment phase [1, 2 or 3]
+    pub fn present_volume_count(&self, roi: &str, phase: usize) -> usize {
+        self.iter()
+            .filter(|r| r.roi_volume(roi, phase).is_some())
+            .count()
+    }
+
+    /// Number of records.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if there are no records.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over the records.
+    pub fn iter(&self) -> std::slice::Iter<'_, Record> {
+        self.0.iter()
+    }
+}
+
+impl From<Vec<Record>> for Records {
+    fn from(v: Vec<Record>) -> Self {
+        Records(v)
+    }
+}
+
+impl Index<usize> for Records {
+    type Output = Record;
+
+    fn index(&self, i: usize) -> &Record {
+        &self.0[i]
+    }
+}
+
+impl IndexMut<usize> for Records {
+    fn index_mut(&mut self, i: usize) -> &mut Record {
+        &mut self.0[i]
+    }
+}
+
+fn deserialize_records<R: std::io::Read>(
+    mut rdr: csv::Reader<R>,
+) -> Result<Vec<Record>, Box<dyn Error>> {
+    let mut v = vec![];
+    for result in rdr.deserialize() {
+        let record = result?;
+        v.push(record);
+    }
+    Ok(v)
+}
+
+/// Statistics of the intra-patient coefficients of variation of a ROI.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CvStat {
+    /// Name of the ROI
+    #[serde(rename = "ROI")]
+    pub roi_name: String,
+    /// Average coefficient of variation
+    #[serde(rename = "average")]
+    pub avg: f64,
+    /// Standard deviation of the coefficients of variation
+    #[serde(rename = "std_dev")]
+    pub std_dev: f64,
+    /// Number of patients from which the data was computed.
+    #[serde(rename = "n")]
+    pub n: usize,
+}
+
+/// Statistics of the ratios of the phase 3 volume to the phase 1 volume of a ROI.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShrinkageRatioStat {
+    /// Name of the ROI
+    #[serde(rename = "ROI")]
+    pub roi_name: String,
+    /// Geometric mean of the ratios
+    pub geometric_mean: f64,
+    /// Geometric standard deviation factor of the ratios
+    pub geometric_sd_factor: f64,
+    /// Number of patients from which the data was computed.
+    pub n: usize,
+}
+
+/// Bland-Altman agreement statistics between the volumes of two phases of a ROI.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BlandAltmanStat {
+    /// Name of the ROI
+    #[serde(rename = "ROI")]
+    pub roi_name: String,
+    /// Phase of the first volume.
+    #[serde(rename = "Phase start")]
+    pub phase_start: i32,
+    /// Phase of the second volume.
+    #[serde(rename = "Phase end")]
+    pub phase_end: i32,
+    /// Average volume difference
+    pub mean_bias: f64,
+    /// Upper limit of agreement
+    pub upper_loa: f64,
+    /// Lower limit of agreement
+    pub lower_loa: f64,
+    /// Fraction of the volume differences within the limits of agreement
+    pub within_loa_fraction: f64,
+}
+
+impl fmt::Display for BlandAltmanStat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} phase {} to {}: bias {:.3}, limits of agreement [{:.3}, {:.3}], {:.1}% within",
+            self.roi_name,
+            self.phase_start,
+            self.phase_end,
+            self.mean_bias,
+            self.lower_loa,
+            self.upper_loa,
+            100.0 * self.within_loa_fraction
+        )
+    }
+}
+
+/// Intraclass correlations between the treatment phases, with their 95% confidence intervals.
+///
+/// Element `[i][j]` relates phase `i + 1` to phase `j + 1`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct IccMatrix {
+    /// Intraclass correlations
+    pub values: [[f64; 3]; 3],
+    /// Lower bounds of the confidence intervals
+    pub ci_lower: [[f64; 3]; 3],
+    /// Upper bounds of the confidence intervals
+    pub ci_upper: [[f64; 3]; 3],
+}
+
+impl fmt::Display for IccMatrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = |i: usize| format!("phase {}", i + 1);
+        write!(f, "{:>w$}", "", w = label(0).len())?;
+        for j in 0..3 {
+            write!(f, " {:>24}", label(j))?;
+        }
+        for i in 0..3 {
+            write!(f, "\n{}", label(i))?;
+            for j in 0..3 {
+                let cell = format!(
+                    "{:.3} [{:.3}, {:.3}]",
+                    self.values[i][j], self.ci_lower[i][j], self.ci_upper[i][j]
+                );
+                write!(f, " {:>24}", cell)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Statistical test recommended for the volume differences.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RecommendedTest {
+    /// Paired t-test
+    #[default]
+    TTest,
+    /// Wilcoxon signed-rank test
+    Wilcoxon,
+}
+
+/// Shape of the distribution of the volume differences.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NormalityCheck {
+    /// Adjusted Fisher-Pearson skewness
+    pub skewness: f64,
+    /// Excess kurtosis
+    pub kurtosis: f64,
+    /// Test recommended based on the skewness and kurtosis
+    pub recommended_test: RecommendedTest,
+}
+
+/// Result of the Cox-Stuart trend test.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CoxStuartResult {
+    /// Number of patients with a volume increase
+    pub n_plus: usize,
+    /// Number of patients with a volume decrease
+    pub n_minus: usize,
+    /// Test statistic of the normal approximation
+    pub z_statistic: f64,
+    /// Two-tailed probability of a test statistic at least as extreme without a trend
+    pub p_value: f64,
+}
+
+/// Result of the Breusch-Pagan test for heteroscedasticity.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct HeteroscedasticityResult {
+    /// Test statistic (n * R^2)
+    pub statistic: f64,
+    /// Degrees of freedom of the chi-squared distribution
+    pub df: usize,
+    /// Probability of a test statistic at least as large under homoscedasticity
+    pub p_value: f64,
+}
+
+/// Iterator over the treatment phases of a ROI, yielding (phase, volumes).
+#[derive(Clone, Debug)]
+pub struct PhaseIter<'a> {
+    data: &'a Data,
+    phase: usize,
+}
+
+impl<'a> Iterator for PhaseIter<'a> {
+    type Item = (usize, &'a [Option<f64>]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let volumes = self.data.phase_volumes(self.phase).ok()?;
+        let phase = self.phase;
+        self.phase += 1;
+        Some((phase, volumes))
+    }
+}
+
+/// Iterator over the treatment phases of a ROI in [EfficientData], yielding (phase, volumes).
+#[derive(Clone, Debug)]
+pub struct EfficientPhaseIter<'a> {
+    data: &'a EfficientData,
+    phase: usize,
+}
+
+impl<'a> Iterator for EfficientPhaseIter<'a> {
+    type Item = (usize, &'a [f64]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let volumes = self.data.phase_volumes(self.phase).ok()?;
+        let phase = self.phase;
+        self.phase += 1;
+        Some((phase, volumes))
+    }
+}
+
+/// Empirical cumulative distribution function (ECDF).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CDF {
+    /// Sorted values
+    pub x_values: Vec<f64>,
+    /// Fraction of the values smaller than or equal to the value at the same index in `x_values`
+    pub probabilities: Vec<f64>,
+}
+
+impl CDF {
+    /// Create the empirical cumulative distribution from a set of values.
+    ///
+    /// NaN values are discarded.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - values from which the distribution is computed
+    pub fn new(mut values: Vec<f64>) -> Self {
+        values.retain(|x| !x.is_nan());
+        values.sort_by(f64::total_cmp);
+        let n = values.len() as f64;
+        let probabilities = (1..=values.len()).map(|i| i as f64 / n).collect();
+        Self {
+            x_values: values,
+            probabilities,
+        }
+    }
+
+    /// Fraction of the values for which the absolute value exceeds a threshold.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - absolute value which needs to be exceeded
+    pub fn probability_of_exceeding(&self, threshold: f64) -> f64 {
+        let n = self.x_values.iter().filter(|x| x.abs() > threshold).count();
+        n as f64 / self.x_values.len() as f64
+    }
+
+    /// Write the distribution as a semicolon delimited CSV with the header `x;probability`.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - destination of the CSV data
+    pub fn to_csv_writer<W: Write>(&self, writer: W) -> Result<(), Box<dyn Error>> {
+        let mut wtr = csv::WriterBuilder::new().delimiter(b';').from_writer(writer);
+        wtr.write_record(["x", "probability"])?;
+        for (x, p) in self.x_values.iter().zip(self.probabilities.iter()) {
+            wtr.write_record([x.to_string(), p.to_string()])?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+/// Volume which is likely reported in the wrong unit.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct UnitInconsistency {
+    /// Patient identifier
+    pub patient_id: String,
+    /// Name of the ROI
+    pub roi: String,
+    /// Treatment phase
+    pub phase: usize,
+    /// Reported volume
+    pub volume: f64,
+    /// Range (min, max) in which the volume was expected
+    pub expected_range: (f64, f64),
+}
+
+/// Options for reading the records from a CSV file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReadOptions {
+    /// Delimiter between the values
+    pub delimiter: u8,
+    /// Columns that must be present in the CSV file, no check is done if empty
+    pub required_columns: Vec<String>,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b';',
+            required_columns: vec![],
+        }
+    }
+}
+
+/// Read the records from a CSV file.
+///
+/// # Arguments
+///
+/// * `filename` - path of the CSV file
+/// * `opts` - options to read the CSV file
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_csv_with_options(filename: &str, opts: &ReadOptions) -> Result<Records, Box<dyn Error>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(opts.delimiter)
+        .has_headers(true)
+        .from_path(filename)?;
+    if !opts.required_columns.is_empty() {
+        let headers = rdr.headers()?;
+        let headers: Vec<_> = headers.iter().collect();
+        let required: Vec<_> = opts.required_columns.iter().map(String::as_str).collect();
+        Records::validate_column_presence(&headers, &required)?;
+    }
+    Ok(Records(deserialize_records(rdr)?))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_csv(filename: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+    let rdr = csv::ReaderBuilder::new()
+        .delimiter(b';')
+        .has_headers(true)
+        .from_path(filename)?;
+    deserialize_records(rdr)
+}
+
+/// Read the volumes of every ROI from a CSV file, discovering the ROIs from the column headers.
+///
+/// Columns named `{ROI}_phase_{N}` are grouped per ROI, in the order in which the ROIs first
+/// appear, and `N` selects the phase [1, 2 or 3] of the volumes. Other columns, such as the
+/// patient ID, are skipped. Every ROI requires the phase 1 and 2 columns, files without phase 3
+/// columns produce `Data` with an empty `vol_phase_3`.
+///
+/// Like [Data::add_vol], the volumes of a patient are discarded for a ROI if one of its phases
+/// is missing.
+///
+/// # Arguments
+///
+/// * `filename` - path of the CSV file
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_csv_to_data(filename: &str) -> Result<Vec<Data>, Box<dyn Error>> {
+    let rdr = csv::ReaderBuilder::new()
+        .delimiter(b';')
+        .has_headers(true)
+        .from_path(filename)?;
+    discover_data(rdr)
+}
+
+/// Read the volumes of every ROI discovered from the column headers, see [read_csv_to_data].
+fn discover_data<R: std::io::Read>(
+    mut rdr: csv::Reader<R>,
+) -> Result<Vec<Data>, Box<dyn Error>> {
+    // (column index, phase) of the volumes per ROI
+    let mut columns: Vec<(String, Vec<(usize, usize)>)> = vec![];
+    for (i, header) in rdr.headers()?.iter().enumerate() {
+        let Some((roi, phase)) = header.rsplit_once("_phase_") else {
+            continue;
+        };
+        let Ok(phase) = phase.parse::<usize>() else {
+            continue;
+        };
+        if !(1..=3).contains(&phase) {
+            return Err(format!("Unsupported phase [{}] in column [{}].", phase, header).into());
+        }
+        match columns.iter_mut().find(|(name, _)| name == roi) {
+            Some((_, v)) if v.iter().any(|(_, p)| *p == phase) => {
+                return Err(format!("Duplicate column [{}].", header).into());
+            }
+            Some((_, v)) => v.push((i, phase)),
+            None => columns.push((roi.to_string(), vec![(i, phase)])),
+        }
+    }
+    for (roi, v) in &columns {
+        if !v.iter().any(|(_, p)| *p == 1) || !v.iter().any(|(_, p)| *p == 2) {
+            return Err(format!("Expected the phase 1 and 2 columns of ROI [{}].", roi).into());
+        }
+    }
+
+    let mut dataset: Vec<_> = columns.iter().map(|(roi, _)| Data::new(roi)).collect();
+    for result in rdr.records() {
+        let row = result?;
+        for ((_, v), data) in columns.iter().zip(dataset.iter_mut()) {
+            let mut vols = vec![];
+            for (i, phase) in v {
+                let cell = row.get(*i).unwrap_or("").trim();
+                let vol = if cell.is_empty() {
+                    f64::NAN
+                } else {
+                    cell.parse::<f64>().map_err(|e| {
+                        format!("Invalid volume [{}] in column [{}]: {}", cell, i + 1, e)
+                    })?
+                };
+                vols.push((*phase, vol));
+            }
+            if vols.iter().any(|(_, vol)| vol.is_nan()) {
+                continue;
+            }
+            for (phase, vol) in vols {
+                match phase {
+                    1 => data.vol_phase_1.push(Some(vol)),
+                    2 => data.vol_phase_2.push(Some(vol)),
+                    _ => data.vol_phase_3.push(Some(vol)),
+                }
+            }
+        }
+    }
+    Ok(dataset)
+}
+
+pub fn records_to_data(records: &Vec<Record>) -> Vec<Data> {
+    let mut gtv = Data::new("GTV");
+    let mut gtv_n = Data::new("GTV_N");
+    let mut ptv_dp = Data::new("PTV_DP");
+    for record in records {
+        gtv.add_vol(
+            record.gtv_phase_i,
+            record.gtv_phase_ii,
+            record.gtv_phase_iii,
+        );
+        gtv_n.add_vol(
+            record.gtv_n_phase_i,
+            record.gtv_n_phase_ii,
+            record.gtv_n_phase_iii,
         );
         ptv_dp.add_vol(
             record.ptv_dp_phase_i,
@@ -192,34 +2131,722 @@ pub fn records_to_data(records: &Vec<Record>) -> Vec<Data> {
             record.ptv_dp_phase_iii,
         );
     }
-    vec![gtv, gtv_n, ptv_dp]
+    vec![gtv, gtv_n, ptv_dp]
+}
+
+/// Volumes of multiple ROIs.
+#[derive(Clone, Debug, Default)]
+pub struct DataSet(pub Vec<Data>);
+
+impl DataSet {
+    /// Number of ROIs.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if there are no ROIs.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over the ROIs.
+    pub fn iter(&self) -> std::slice::Iter<'_, Data> {
+        self.0.iter()
+    }
+
+    /// Remove a ROI.
+    ///
+    /// Returns true if a ROI with the name was present.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - ROI name
+    pub fn drop_roi(&mut self, name: &str) -> bool {
+        let n = self.0.len();
+        self.0.retain(|data| data.roi_name != name);
+        self.0.len() != n
+    }
+
+    /// Keep only the ROIs with the given names.
+    ///
+    /// # Arguments
+    ///
+    /// * `names` - names of the ROIs to keep
+    pub fn retain_rois(&mut self, names: &[&str]) {
+        self.0.retain(|data| names.contains(&data.roi_name.as_str()));
+    }
+
+    /// Rename a ROI.
+    ///
+    /// An error is returned if there is no ROI named `old_name` or if another ROI named
+    /// `new_name` already exists. Renaming a ROI to its own name does nothing.
+    ///
+    /// # Arguments
+    ///
+    /// * `old_name` - current ROI name
+    /// * `new_name` - new ROI name
+    pub fn rename_roi(&mut self, old_name: &str, new_name: &str) -> Result<(), Box<dyn Error>> {
+        let i = self
+            .0
+            .iter()
+            .position(|data| data.roi_name == old_name)
+            .ok_or_else(|| format!("Unknown ROI [{}] in the dataset.", old_name))?;
+        if new_name == old_name {
+            return Ok(());
+        }
+        if self.0.iter().any(|data| data.roi_name == new_name) {
+            return Err(format!("Duplicate ROI [{}] in the dataset.", new_name).into());
+        }
+        self.0[i].roi_name = new_name.to_string();
+        Ok(())
+    }
+}
+
+impl From<Vec<Data>> for DataSet {
+    fn from(v: Vec<Data>) -> Self {
+        DataSet(v)
+    }
+}
+
+/// Stores statical data (average and standard deviation) per ROI and phase change.
+///
+/// Besides the fields, the serialised form contains the Hedges' g effect size and its 95%
+/// confidence interval (`hedges_g`, `hedges_g_ci_lower` and `hedges_g_ci_upper`).
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Stat {
+    /// Name of the ROI
+    #[serde(rename = "ROI")]
+    roi_name: String,
+    /// Average volume at the phase when the initial data was acquired.
+    #[serde(rename = "Volume Phase start")]
+    avg_vol_phase_start: f64,
+    /// Phase at which the initial data was acquired.
+    #[serde(rename = "Phase start")]
+    phase_start: i32,
+    /// Phase at which the last data was acquired.
+    #[serde(rename = "Phase end")]
+    phase_end: i32,
+    /// Average volume difference
+    #[serde(rename = "average")]
+    avg: f64,
+    /// Standard deviation of the volume differences
+    #[serde(rename = "std_dev")]
+    std_dev: f64,
+    /// Number of data points from which the data was computed.
+    #[serde(rename = "n")]
+    n: usize,
+    /// Average relative volume change [%]
+    #[serde(rename = "average_pct", default)]
+    avg_pct: f64,
+    /// Standard deviation of the relative volume changes [%]
+    #[serde(rename = "std_dev_pct", default)]
+    std_dev_pct: f64,
+    /// Number of data points from which the relative volume changes were computed, patients
+    /// with a zero volume at the start phase are excluded.
+    #[serde(rename = "n_pct", default)]
+    n_pct: usize,
+}
+
+impl Stat {
+    /// Create a builder to construct a validated `Stat`.
+    pub fn builder() -> StatBuilder {
+        StatBuilder::default()
+    }
+
+    /// Read statistics from a CSV file written by [write_stats_csv].
+    ///
+    /// The round trip is lossy, the floating point values are only as precise as the
+    /// `float_precision` of the [CsvWriterOptions] used to write the file (6 decimals by
+    /// default).
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - path of the semicolon delimited CSV file
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_csv_file(path: &str) -> Result<Vec<Stat>, Box<dyn Error>> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(CsvWriterOptions::default().delimiter)
+            .has_headers(true)
+            .from_path(path)?;
+        let mut v = vec![];
+        for result in rdr.deserialize() {
+            let stat = result?;
+            v.push(stat);
+        }
+        Ok(v)
+    }
+
+    /// Name of the ROI
+    pub fn roi_name(&self) -> &str {
+        &self.roi_name
+    }
+
+    /// Average volume at the phase when the initial data was acquired.
+    pub fn avg_vol_phase_start(&self) -> f64 {
+        self.avg_vol_phase_start
+    }
+
+    /// Phase at which the initial data was acquired.
+    pub fn phase_start(&self) -> i32 {
+        self.phase_start
+    }
+
+    /// Phase at which the last data was acquired.
+    pub fn phase_end(&self) -> i32 {
+        self.phase_end
+    }
+
+    /// Average volume difference
+    pub fn avg(&self) -> f64 {
+        self.avg
+    }
+
+    /// Standard deviation of the volume differences
+    pub fn std_dev(&self) -> f64 {
+        self.std_dev
+    }
+
+    /// Number of data points from which the data was computed.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// Average relative volume change [%]
+    pub fn avg_pct(&self) -> f64 {
+        self.avg_pct
+    }
+
+    /// Standard deviation of the relative volume changes [%]
+    pub fn std_dev_pct(&self) -> f64 {
+        self.std_dev_pct
+    }
+
+    /// Number of data points from which the relative volume changes were computed.
+    pub fn n_pct(&self) -> usize {
+        self.n_pct
+    }
+
+    /// Express the average and standard deviation relative to the baseline volume of a
+    /// reference ROI, making volume changes of different ROIs comparable.
+    ///
+    /// # Arguments
+    ///
+    /// * `reference` - statistics of the reference ROI, its average volume at the start phase
+    ///   is used as the divisor
+    pub fn normalize_by_roi_size(&self, reference: &Stat) -> Result<Stat, Box<dyn Error>> {
+        if reference.avg_vol_phase_start == 0.0 {
+            return Err(format!(
+                "Division by zero, the reference ROI [{}] has no volume at the start phase.",
+                reference.roi_name
+            )
+                .into());
+        }
+        Ok(Stat {
+            avg: self.avg / reference.avg_vol_phase_start,
+            std_dev: self.std_dev / reference.avg_vol_phase_start,
+            ..self.clone()
+        })
+    }
+
+    /// R list literal of the statistics, e.g.
+    /// `list(roi="GTV", phase_start=1, phase_end=2, avg=-5.23, sd=3.11, n=18L)`.
+    pub fn to_r_list(&self) -> String {
+        format!(
+            "list(roi={}, phase_start={}, phase_end={}, avg={}, sd={}, n={}L)",
+            r_string(&self.roi_name),
+            self.phase_start,
+            self.phase_end,
+            r_double(self.avg),
+            r_double(self.std_dev),
+            self.n
+        )
+    }
+
+    /// Returns true if both average volume differences indicate the same direction of change:
+    /// shrinkage (positive), growth (negative) or no change.
+    ///
+    /// An average of exactly zero (`0.0` or `-0.0`) is treated as no change, so it is only
+    /// consistent with another zero average. A NaN average is never consistent.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - statistics to compare with, e.g. of a reference institution
+    pub fn sign_consistency_with(&self, other: &Stat) -> bool {
+        match (self.avg.partial_cmp(&0.0), other.avg.partial_cmp(&0.0)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Returns true if the average volume differences are within `tolerance_sd` combined
+    /// standard deviations (`hypot` of both standard deviations) of each other.
+    ///
+    /// The bound is inclusive, so a tolerance of 0 requires equal averages. This is a pragmatic
+    /// agreement criterion and not a formal two-sample test.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - statistics to compare with, e.g. of another institution
+    /// * `tolerance_sd` - allowed difference in number of combined standard deviations
+    pub fn agrees_with(&self, other: &Stat, tolerance_sd: f64) -> bool {
+        (self.avg - other.avg).abs() <= tolerance_sd * self.std_dev.hypot(other.std_dev)
+    }
+
+    /// Clinical magnitude of the average volume difference, using the default thresholds.
+    ///
+    /// Returns None if the average is NaN, e.g. for a ROI without volumes.
+    pub fn magnitude_category(&self) -> Option<MagnitudeCategory> {
+        self.magnitude_category_with(&MagnitudeThresholds::default())
+    }
+
+    /// Clinical magnitude of the average volume difference.
+    ///
+    /// Returns None if the average is NaN, e.g. for a ROI without volumes.
+    ///
+    /// # Arguments
+    ///
+    /// * `thresholds` - lower bounds of the magnitude categories
+    pub fn magnitude_category_with(
+        &self,
+        thresholds: &MagnitudeThresholds,
+    ) -> Option<MagnitudeCategory> {
+        thresholds.classify(self.avg.abs())
+    }
+
+    /// Confidence interval (lower, upper) of the average volume difference,
+    /// `avg ± t(1 - alpha / 2, n - 1) * std_dev / sqrt(n)`.
+    ///
+    /// Returns None if there are less than 2 data points or `alpha` is not in (0, 1).
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha` - significance level, e.g. 0.05 for a 95% confidence interval
+    pub fn confidence_interval(&self, alpha: f64) -> Option<(f64, f64)> {
+        if self.n < 2 || !(alpha > 0.0 && alpha < 1.0) {
+            return None;
+        }
+        let t = StudentsT::new(0.0, 1.0, (self.n - 1) as f64)
+            .ok()?
+            .inverse_cdf(1.0 - alpha / 2.0);
+        let half_width = t * self.std_dev / (self.n as f64).sqrt();
+        Some((self.avg - half_width, self.avg + half_width))
+    }
+
+    /// Confidence interval of the average volume difference formatted as `[lower, upper]`.
+    ///
+    /// The bounds are NaN if the confidence interval can't be computed, see
+    /// [Stat::confidence_interval].
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha` - significance level, e.g. 0.05 for a 95% confidence interval
+    pub fn format_ci(&self, alpha: f64) -> String {
+        let (lower, upper) = self.confidence_interval(alpha).unwrap_or((f64::NAN, f64::NAN));
+        format!("[{}, {}]", lower, upper)
+    }
+
+    /// Cohen's d effect size of the paired volume differences (average / standard deviation).
+    ///
+    /// Returns None if there are less than 2 data points or the effect size isn't finite.
+    pub fn cohens_d(&self) -> Option<f64> {
+        if self.n < 2 {
+            return None;
+        }
+        Some(self.avg / self.std_dev).filter(|d| d.is_finite())
+    }
+
+    /// Hedges' g effect size, Cohen's d corrected for the small sample bias with
+    /// `J(df) = 1 - 3 / (4 * df - 1)` where `df = n - 1`.
+    ///
+    /// Returns None if there are less than 2 data points or the effect size isn't finite.
+    pub fn hedges_g(&self) -> Option<f64> {
+        let d = self.cohens_d()?;
+        let df = (self.n - 1) as f64;
+        Some(d * (1.0 - 3.0 / (4.0 * df - 1.0)))
+    }
+
+    /// 95% confidence interval (lower, upper) of Hedges' g, using the standard error
+    /// `sqrt(1 / n + g^2 / (2 * n))`.
+    ///
+    /// Returns None if there are less than 2 data points or the effect size isn't finite.
+    pub fn hedges_g_ci(&self) -> Option<(f64, f64)> {
+        let g = self.hedges_g()?;
+        let n = self.n as f64;
+        let se = (1.0 / n + g * g / (2.0 * n)).sqrt();
+        Some((g - 1.96 * se, g + 1.96 * se))
+    }
+
+    /// Check the invariants of the statistics: a finite average, a finite and positive
+    /// standard deviation, at least one data point, different start and end phases and a
+    /// finite and positive average volume at the start phase.
+    pub fn is_valid(&self) -> bool {
+        self.check_invariants().is_ok()
+    }
+
+    /// Check the invariants of the statistics, see [Stat::is_valid], returning an error which
+    /// describes the first violated invariant.
+    fn check_invariants(&self) -> Result<(), Box<dyn Error>> {
+        if !self.avg.is_finite() {
+            return Err(format!("Expected a finite average [{}].", self.avg).into());
+        }
+        if !self.std_dev.is_finite() || self.std_dev < 0.0 {
+            return Err(format!(
+                "Expected a finite and positive standard deviation [{}].",
+                self.std_dev
+            )
+                .into());
+        }
+        if self.n < 1 {
+            return Err("Expected at least one data point.".into());
+        }
+        if self.phase_start == self.phase_end {
+            return Err(format!(
+                "Expected a different start and end phase [{}].",
+                self.phase_start
+            )
+                .into());
+        }
+        if !self.avg_vol_phase_start.is_finite() || self.avg_vol_phase_start < 0.0 {
+            return Err(format!(
+                "Expected a finite and positive average volume at the start phase [{}].",
+                self.avg_vol_phase_start
+            )
+                .into());
+        }
+        Ok(())
+    }
+
+    /// Update the average, standard deviation and number of data points with a new volume
+    /// difference using Welford's online algorithm.
+    ///
+    /// The running sum of squared deviations is recovered from the standard deviation and the
+    /// number of data points, so statistics read from a JSON file can be updated as well. The
+    /// relative volume changes are left unchanged, as they require the volume at the start phase.
+    ///
+    /// # Arguments
+    ///
+    /// * `new_diff` - volume difference of a new patient, NaN values are discarded
+    pub fn update(&mut self, new_diff: f64) {
+        if new_diff.is_nan() {
+            return;
+        }
+        let (avg, m2) = if self.n == 0 {
+            (0.0, 0.0)
+        } else if self.n == 1 {
+            (self.avg, 0.0)
+        } else {
+            (self.avg, self.std_dev.powi(2) * (self.n - 1) as f64)
+        };
+        let n = self.n + 1;
+        let delta = new_diff - avg;
+        let avg = avg + delta / n as f64;
+        let m2 = m2 + delta * (new_diff - avg);
+        self.avg = avg;
+        self.std_dev = (m2 / (n - 1) as f64).sqrt();
+        self.n = n;
+    }
+
+    /// Update the statistics with multiple new volume differences.
+    ///
+    /// # Arguments
+    ///
+    /// * `diffs` - volume differences of the new patients
+    pub fn update_batch(&mut self, diffs: &[f64]) {
+        for diff in diffs {
+            self.update(*diff);
+        }
+    }
+
+    /// Chain the statistics of two consecutive phase intervals of the same ROI.
+    ///
+    /// The averages are summed and the variances are pooled, ignoring the correlation of the
+    /// volume changes within a patient. The relative volume changes are compounded and their
+    /// standard deviations propagated to first order. The number of data points is the smallest
+    /// of both.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - statistics starting at the end phase of `self`
+    pub fn merge_with_phase_shift(&self, other: &Stat) -> Result<Stat, Box<dyn Error>> {
+        if self.roi_name != other.roi_name {
+            return Err(format!(
+                "Expected the same ROI to merge statistics [{}] and [{}].",
+                self.roi_name, other.roi_name
+            )
+                .into());
+        }
+        if self.phase_end != other.phase_start {
+            return Err(format!(
+                "Phase gap between the end phase [{}] and the start phase [{}].",
+                self.phase_end, other.phase_start
+            )
+                .into());
+        }
+        Ok(Stat {
+            roi_name: self.roi_name.clone(),
+            avg_vol_phase_start: self.avg_vol_phase_start,
+            phase_start: self.phase_start,
+            phase_end: other.phase_end,
+            avg: self.avg + other.avg,
+            std_dev: self.std_dev.hypot(other.std_dev),
+            n: self.n.min(other.n),
+            avg_pct: ((1.0 + self.avg_pct / 100.0) * (1.0 + other.avg_pct / 100.0) - 1.0) * 100.0,
+            std_dev_pct: ((1.0 + other.avg_pct / 100.0) * self.std_dev_pct)
+                .hypot((1.0 + self.avg_pct / 100.0) * other.std_dev_pct),
+            n_pct: self.n_pct.min(other.n_pct),
+        })
+    }
+}
+
+impl Serialize for Stat {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let ci = self.hedges_g_ci();
+        let mut state = serializer.serialize_struct("Stat", 13)?;
+        state.serialize_field("ROI", &self.roi_name)?;
+        state.serialize_field("Volume Phase start", &self.avg_vol_phase_start)?;
+        state.serialize_field("Phase start", &self.phase_start)?;
+        state.serialize_field("Phase end", &self.phase_end)?;
+        state.serialize_field("average", &self.avg)?;
+        state.serialize_field("std_dev", &self.std_dev)?;
+        state.serialize_field("n", &self.n)?;
+        state.serialize_field("average_pct", &self.avg_pct)?;
+        state.serialize_field("std_dev_pct", &self.std_dev_pct)?;
+        state.serialize_field("n_pct", &self.n_pct)?;
+        state.serialize_field("hedges_g", &self.hedges_g())?;
+        state.serialize_field("hedges_g_ci_lower", &ci.map(|ci| ci.0))?;
+        state.serialize_field("hedges_g_ci_upper", &ci.map(|ci| ci.1))?;
+        state.end()
+    }
+}
+
+/// Fraction of the statistics with a matching reference statistic (same ROI and phases) for
+/// which the average volume differences indicate the same direction of change, see
+/// [Stat::sign_consistency_with].
+///
+/// Returns NaN if none of the statistics has a matching reference statistic.
+///
+/// # Arguments
+///
+/// * `stats` - statistics to check
+/// * `reference` - reference statistics, e.g. of a reference institution
+pub fn sign_consistent_fraction(stats: &[Stat], reference: &[Stat]) -> f64 {
+    let matched: Vec<_> = stats
+        .iter()
+        .filter_map(|stat| reference.iter().find(|r| *r == stat).map(|r| (stat, r)))
+        .collect();
+    let consistent = matched
+        .iter()
+        .filter(|(stat, r)| stat.sign_consistency_with(r))
+        .count();
+    consistent as f64 / matched.len() as f64
+}
+
+/// R string literal.
+fn r_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// R double literal.
+fn r_double(x: f64) -> String {
+    if x.is_nan() {
+        "NaN".to_string()
+    } else if x == f64::INFINITY {
+        "Inf".to_string()
+    } else if x == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else {
+        x.to_string()
+    }
+}
+
+/// R `data.frame(...)` call with one row per statistic, using the columns of
+/// [Stat::to_r_list].
+///
+/// # Arguments
+///
+/// * `stats` - statistics to convert
+pub fn to_r_dataframe(stats: &[Stat]) -> String {
+    let column = |f: &dyn Fn(&Stat) -> String| -> String {
+        let v: Vec<_> = stats.iter().map(f).collect();
+        format!("c({})", v.join(", "))
+    };
+    format!(
+        "data.frame(roi={}, phase_start={}, phase_end={}, avg={}, sd={}, n={})",
+        column(&|s| r_string(&s.roi_name)),
+        column(&|s| s.phase_start.to_string()),
+        column(&|s| s.phase_end.to_string()),
+        column(&|s| r_double(s.avg)),
+        column(&|s| r_double(s.std_dev)),
+        column(&|s| format!("{}L", s.n))
+    )
+}
+
+/// Clinical magnitude of a volume change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum MagnitudeCategory {
+    Negligible,
+    Minor,
+    Moderate,
+    Major,
+    Extreme,
+}
+
+/// Lower bounds [cc] of the magnitude categories, volume changes below `minor` are negligible.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MagnitudeThresholds {
+    pub minor: f64,
+    pub moderate: f64,
+    pub major: f64,
+    pub extreme: f64,
+}
+
+impl Default for MagnitudeThresholds {
+    fn default() -> Self {
+        Self {
+            minor: 2.0,
+            moderate: 5.0,
+            major: 10.0,
+            extreme: 20.0,
+        }
+    }
 }
 
-/// Stores statical data (average and standard deviation) per ROI and phase change.
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub struct Stat {
+impl MagnitudeThresholds {
+    /// Create the thresholds, an error is returned if they are not increasing.
+    pub fn new(
+        minor: f64,
+        moderate: f64,
+        major: f64,
+        extreme: f64,
+    ) -> Result<Self, Box<dyn Error>> {
+        if !(0.0 <= minor && minor <= moderate && moderate <= major && major <= extreme) {
+            return Err(format!(
+                "Expected increasing positive magnitude thresholds [{}, {}, {}, {}].",
+                minor, moderate, major, extreme
+            )
+                .into());
+        }
+        Ok(Self {
+            minor,
+            moderate,
+            major,
+            extreme,
+        })
+    }
+
+    /// Magnitude category of an absolute volume change, None if the volume change is NaN.
+    pub fn classify(&self, volume_change: f64) -> Option<MagnitudeCategory> {
+        if volume_change.is_nan() {
+            return None;
+        }
+        let category = if volume_change >= self.extreme {
+            MagnitudeCategory::Extreme
+        } else if volume_change >= self.major {
+            MagnitudeCategory::Major
+        } else if volume_change >= self.moderate {
+            MagnitudeCategory::Moderate
+        } else if volume_change >= self.minor {
+            MagnitudeCategory::Minor
+        } else {
+            MagnitudeCategory::Negligible
+        };
+        Some(category)
+    }
+}
+
+impl FromStr for MagnitudeThresholds {
+    type Err = Box<dyn Error>;
+
+    /// Parse a comma separated list of four thresholds, e.g. `2,5,10,20`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let v = s
+            .split(',')
+            .map(|t| t.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()?;
+        if v.len() != 4 {
+            return Err(format!("Expected four magnitude thresholds [{}].", s).into());
+        }
+        Self::new(v[0], v[1], v[2], v[3])
+    }
+}
+
+/// Builder for [Stat] which validates the values before creating an instance.
+#[derive(Clone, Debug, Default)]
+pub struct StatBuilder {
+    stat: Stat,
+}
+
+impl StatBuilder {
     /// Name of the ROI
-    #[serde(rename = "ROI")]
-    pub roi_name: String,
+    pub fn roi_name(mut self, roi_name: &str) -> Self {
+        self.stat.roi_name = roi_name.to_string();
+        self
+    }
+
     /// Average volume at the phase when the initial data was acquired.
-    #[serde(rename = "Volume Phase start")]
-    pub avg_vol_phase_start: f64,
+    pub fn avg_vol_phase_start(mut self, avg_vol_phase_start: f64) -> Self {
+        self.stat.avg_vol_phase_start = avg_vol_phase_start;
+        self
+    }
+
     /// Phase at which the initial data was acquired.
-    #[serde(rename = "Phase start")]
-    pub phase_start: i32,
+    pub fn phase_start(mut self, phase_start: i32) -> Self {
+        self.stat.phase_start = phase_start;
+        self
+    }
+
     /// Phase at which the last data was acquired.
-    #[serde(rename = "Phase end")]
-    pub phase_end: i32,
+    pub fn phase_end(mut self, phase_end: i32) -> Self {
+        self.stat.phase_end = phase_end;
+        self
+    }
+
     /// Average volume difference
-    #[serde(rename = "average")]
-    pub avg: f64,
+    pub fn avg(mut self, avg: f64) -> Self {
+        self.stat.avg = avg;
+        self
+    }
+
     /// Standard deviation of the volume differences
-    #[serde(rename = "std_dev")]
-    pub std_dev: f64,
+    pub fn std_dev(mut self, std_dev: f64) -> Self {
+        self.stat.std_dev = std_dev;
+        self
+    }
+
     /// Number of data points from which the data was computed.
-    #[serde(rename = "n")]
-    pub n: usize,
+    pub fn n(mut self, n: usize) -> Self {
+        self.stat.n = n;
+        self
+    }
+
+    /// Average relative volume change [%]
+    pub fn avg_pct(mut self, avg_pct: f64) -> Self {
+        self.stat.avg_pct = avg_pct;
+        self
+    }
+
+    /// Standard deviation of the relative volume changes [%]
+    pub fn std_dev_pct(mut self, std_dev_pct: f64) -> Self {
+        self.stat.std_dev_pct = std_dev_pct;
+        self
+    }
+
+    /// Number of data points from which the relative volume changes were computed.
+    pub fn n_pct(mut self, n_pct: usize) -> Self {
+        self.stat.n_pct = n_pct;
+        self
+    }
+
+    /// Create the `Stat`.
+    ///
+    /// An error is returned if the statistics violate one of the invariants checked by
+    /// [Stat::is_valid].
+    pub fn build(self) -> Result<Stat, Box<dyn Error>> {
+        self.stat.check_invariants()?;
+        Ok(self.stat)
+    }
 }
 
 impl PartialEq for Stat {
@@ -254,6 +2881,10 @@ impl PartialOrd for Stat {
 
 fn add_data(stats: &mut Vec<Stat>, data: &Data) -> Result<(), Box<dyn Error>> {
     stats.push(data.phase_1_to_2_stat()?);
+    // Data without phase 3 volumes, e.g. from a two-phase file
+    if data.vol_phase_3.is_empty() && !data.vol_phase_2.is_empty() {
+        return Ok(());
+    }
     stats.push(data.phase_2_to_3_stat()?);
     Ok(())
 }
@@ -264,5 +2895,864 @@ pub fn dataset_to_stats(dataset: &Vec<Data>) -> Result<Vec<Stat>, Box<dyn Error>
         add_data(&mut v, data)?;
     }
     v.sort();
+    for stat in v.iter().filter(|stat| !stat.is_valid()) {
+        warn!("Invalid statistics: {:?}", stat);
+    }
+    Ok(v)
+}
+
+/// Method to exclude outlying volume differences.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutlierMethod {
+    /// Keep all the volume differences
+    #[default]
+    None,
+    /// Exclude volume differences with an absolute z-score above a threshold, all differences
+    /// are kept if their standard deviation is zero or undefined
+    ZScore,
+    /// Exclude a fraction of the smallest and the same fraction of the largest volume
+    /// differences
+    Trim,
+}
+
+/// Configuration of the outlier exclusion of one analysis in a comparison study.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisConfig {
+    /// Method to exclude outliers
+    pub outlier_method: OutlierMethod,
+    /// Absolute z-score above which a volume difference is excluded, required for
+    /// [OutlierMethod::ZScore]
+    pub z_threshold: Option<f64>,
+    /// Fraction [0, 0.5) excluded at each end, required for [OutlierMethod::Trim]
+    pub trim_fraction: Option<f64>,
+}
+
+impl AnalysisConfig {
+    /// Exclude the outliers from the (patient index, volume difference) pairs.
+    fn exclude_outliers(
+        &self,
+        mut diffs: Vec<(usize, f64)>,
+    ) -> Result<Vec<(usize, f64)>, Box<dyn Error>> {
+        match self.outlier_method {
+            OutlierMethod::None => Ok(diffs),
+            OutlierMethod::ZScore => {
+                let z = self
+                    .z_threshold
+                    .ok_or("A z-score threshold is required for the z-score outlier method.")?;
+                let v: Vec<_> = diffs.iter().map(|(_, d)| *d).collect();
+                let (avg, std_dev, _) = avg_std_dev(&v);
+                // Without spread (identical differences or a single patient) the z-scores
+                // are undefined and none of the differences is an outlier.
+                if std_dev.is_finite() && std_dev > 0.0 {
+                    diffs.retain(|(_, d)| ((d - avg) / std_dev).abs() <= z);
+                }
+                Ok(diffs)
+            }
+            OutlierMethod::Trim => {
+                let fraction = self
+                    .trim_fraction
+                    .ok_or("A trim fraction is required for the trim outlier method.")?;
+                if !(0.0..0.5).contains(&fraction) {
+                    return Err(
+                        format!("Expected a trim fraction in [0, 0.5) [{}].", fraction).into()
+                    );
+                }
+                diffs.sort_by(|a, b| a.1.total_cmp(&b.1));
+                let k = (diffs.len() as f64 * fraction).floor() as usize;
+                Ok(diffs[k..diffs.len() - k].to_vec())
+            }
+        }
+    }
+}
+
+/// Compute the statistics of the volume changes between two phases for several outlier
+/// exclusion configurations, to assess the sensitivity of the results to the method.
+///
+/// # Arguments
+///
+/// * `data` - volumes of a ROI
+/// * `start` - treatment phase at the start [1, 2 or 3]
+/// * `end` - treatment phase at the end [1, 2 or 3]
+/// * `configs` - outlier exclusion configurations
+pub fn run_comparison_study(
+    data: &Data,
+    start: usize,
+    end: usize,
+    configs: &[AnalysisConfig],
+) -> Result<Vec<(AnalysisConfig, Stat)>, Box<dyn Error>> {
+    let diffs = data.phase_differences(start, end)?;
+    let mut v = vec![];
+    for config in configs {
+        let retained = config.exclude_outliers(diffs.clone())?;
+        let stat = data.stat_from_differences(start, end, &retained)?;
+        v.push((config.clone(), stat));
+    }
     Ok(v)
 }
+
+/// Options for [pipeline_report].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PipelineOptions {
+    /// Path of the CSV input file
+    pub file: String,
+    /// Delimiter between the values in the CSV input file
+    pub delimiter: u8,
+    /// ROIs included in the statistics, all ROIs are included if empty
+    pub roi_filter: Vec<String>,
+}
+
+impl Default for PipelineOptions {
+    fn default() -> Self {
+        Self {
+            file: String::new(),
+            delimiter: b';',
+            roi_filter: vec![],
+        }
+    }
+}
+
+impl PipelineOptions {
+    /// Create a builder to construct validated `PipelineOptions`.
+    pub fn builder() -> PipelineOptionsBuilder {
+        PipelineOptionsBuilder::default()
+    }
+
+    /// Options to read the CSV input file.
+    pub fn read_options(&self) -> ReadOptions {
+        ReadOptions {
+            delimiter: self.delimiter,
+            ..Default::default()
+        }
+    }
+}
+
+/// Builder for [PipelineOptions].
+#[derive(Clone, Debug, Default)]
+pub struct PipelineOptionsBuilder {
+    file: Option<String>,
+    delimiter: Option<u8>,
+    roi_filter: Vec<String>,
+}
+
+impl PipelineOptionsBuilder {
+    /// Path of the CSV input file
+    pub fn file(mut self, path: &str) -> Self {
+        self.file = Some(path.to_string());
+        self
+    }
+
+    /// Delimiter between the values in the CSV input file [default: `;`]
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = Some(delimiter);
+        self
+    }
+
+    /// ROIs included in the statistics
+    pub fn roi_filter(mut self, rois: &[&str]) -> Self {
+        self.roi_filter = rois.iter().map(|roi| roi.to_string()).collect();
+        self
+    }
+
+    /// Create the `PipelineOptions`, an error is returned if the input file is not set.
+    pub fn build(self) -> Result<PipelineOptions, Box<dyn Error>> {
+        let file = self.file.ok_or("The CSV input file of the pipeline is not set.")?;
+        let defaults = PipelineOptions::default();
+        Ok(PipelineOptions {
+            file,
+            delimiter: self.delimiter.unwrap_or(defaults.delimiter),
+            roi_filter: self.roi_filter,
+        })
+    }
+}
+
+/// Outputs of the analysis of a CSV input file.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PipelineReport {
+    /// Number of records read from the CSV input file
+    pub records_loaded: usize,
+    /// Completeness of the volumes in the CSV input file
+    pub data_quality_report: DataQualityReport,
+    /// Statistics per ROI and phase change
+    pub stats: Vec<Stat>,
+    /// Statistics of the phase 3 to phase 1 volume ratios per ROI
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub shrinkage_ratios: Vec<ShrinkageRatioStat>,
+    /// Duration of the analysis in milliseconds
+    pub elapsed_ms: u64,
+}
+
+/// Read a CSV input file and compute the statistics of the volume changes in one call.
+///
+/// # Arguments
+///
+/// * `opts` - input file and options of the analysis
+#[cfg(not(target_arch = "wasm32"))]
+pub fn pipeline_report(opts: &PipelineOptions) -> Result<PipelineReport, Box<dyn Error>> {
+    let start = std::time::Instant::now();
+    let records = read_csv_with_options(&opts.file, &opts.read_options())?;
+    let mut dataset = DataSet::from(records_to_data(&records.0));
+    if !opts.roi_filter.is_empty() {
+        let rois: Vec<_> = opts.roi_filter.iter().map(String::as_str).collect();
+        dataset.retain_rois(&rois);
+    }
+    let stats = dataset_to_stats(&dataset.0)?;
+    let shrinkage_ratios = dataset
+        .iter()
+        .map(Data::shrinkage_ratio_stat)
+        .filter(|stat| stat.n > 0)
+        .collect();
+    Ok(PipelineReport {
+        records_loaded: records.len(),
+        data_quality_report: records.data_quality_report(),
+        stats,
+        shrinkage_ratios,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+    })
+}
+
+/// Outcome of processing multiple input files, a failing file doesn't stop the others.
+#[derive(Debug, Default)]
+pub struct BatchResult<T, E> {
+    /// Input files which were processed successfully and their result
+    pub successes: Vec<(PathBuf, T)>,
+    /// Input files which couldn't be processed and their error
+    pub failures: Vec<(PathBuf, E)>,
+}
+
+impl<T, E> BatchResult<T, E> {
+    /// Returns true if none of the input files failed.
+    pub fn all_succeeded(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Results of the successful input files.
+    ///
+    /// The errors are only returned if all input files failed.
+    pub fn into_result(self) -> Result<Vec<T>, Vec<E>> {
+        if self.successes.is_empty() && !self.failures.is_empty() {
+            Err(self.failures.into_iter().map(|(_, e)| e).collect())
+        } else {
+            Ok(self.successes.into_iter().map(|(_, t)| t).collect())
+        }
+    }
+}
+
+impl<T, E: fmt::Display> BatchResult<T, E> {
+    /// Print the summary table of the successes and failures to stderr.
+    pub fn eprint_summary(&self) {
+        eprint!("{}", self);
+    }
+}
+
+impl<T, E: fmt::Display> fmt::Display for BatchResult<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<8} File", "Status")?;
+        for (path, _) in &self.successes {
+            writeln!(f, "{:<8} {}", "OK", path.display())?;
+        }
+        for (path, e) in &self.failures {
+            writeln!(f, "{:<8} {}: {}", "FAILED", path.display(), e)?;
+        }
+        writeln!(
+            f,
+            "{} succeeded, {} failed",
+            self.successes.len(),
+            self.failures.len()
+        )
+    }
+}
+
+/// Compute the statistics of the volume changes of multiple CSV input files.
+///
+/// Each file is processed with the default [ReadOptions], a file which can't be read or
+/// analysed is added to the failures and the remaining files are still processed.
+///
+/// # Arguments
+///
+/// * `paths` - paths of the CSV input files
+#[cfg(not(target_arch = "wasm32"))]
+pub fn batch_process(paths: &[PathBuf]) -> BatchResult<Vec<Stat>, Box<dyn Error>> {
+    let mut result = BatchResult {
+        successes: vec![],
+        failures: vec![],
+    };
+    for path in paths {
+        let stats = path
+            .to_str()
+            .ok_or_else(|| format!("Invalid path [{}].", path.display()).into())
+            .and_then(|filename| read_csv_with_options(filename, &ReadOptions::default()))
+            .and_then(|records| dataset_to_stats(&records_to_data(&records.0)));
+        match stats {
+            Ok(stats) => result.successes.push((path.clone(), stats)),
+            Err(e) => result.failures.push((path.clone(), e)),
+        }
+    }
+    result
+}
+
+/// Line ending used when writing CSV files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`
+    #[default]
+    Unix,
+    /// `\r\n`
+    Windows,
+}
+
+/// Options controlling the format of the CSV written by [write_stats_csv_with_options].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsvWriterOptions {
+    /// Delimiter between the values
+    pub delimiter: u8,
+    /// Number of digits after the decimal point of floating point values
+    pub float_precision: usize,
+    /// Write the header as the first line
+    pub include_header: bool,
+    /// Line ending after each line
+    pub line_ending: LineEnding,
+}
+
+impl Default for CsvWriterOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b';',
+            float_precision: 6,
+            include_header: true,
+            line_ending: LineEnding::Unix,
+        }
+    }
+}
+
+/// Write the statistics as CSV using the default [CsvWriterOptions].
+///
+/// # Arguments
+///
+/// * `stats` - statistics to write
+/// * `writer` - destination of the CSV data
+pub fn write_stats_csv<W: Write>(stats: &[Stat], writer: &mut W) -> Result<(), Box<dyn Error>> {
+    write_stats_csv_with_options(stats, writer, &CsvWriterOptions::default())
+}
+
+/// Write the statistics as CSV.
+///
+/// The column names match the field names of the JSON output.
+///
+/// # Arguments
+///
+/// * `stats` - statistics to write
+/// * `writer` - destination of the CSV data
+/// * `opts` - format of the CSV data
+pub fn write_stats_csv_with_options<W: Write>(
+    stats: &[Stat],
+    writer: &mut W,
+    opts: &CsvWriterOptions,
+) -> Result<(), Box<dyn Error>> {
+    let terminator = match opts.line_ending {
+        LineEnding::Unix => csv::Terminator::Any(b'\n'),
+        LineEnding::Windows => csv::Terminator::CRLF,
+    };
+    let mut wtr = csv::WriterBuilder::new()
+        .delimiter(opts.delimiter)
+        .terminator(terminator)
+        .from_writer(writer);
+    if opts.include_header {
+        wtr.write_record([
+            "ROI",
+            "Volume Phase start",
+            "Phase start",
+            "Phase end",
+            "average",
+            "std_dev",
+            "n",
+            "average_pct",
+            "std_dev_pct",
+            "n_pct",
+        ])?;
+    }
+    let p = opts.float_precision;
+    for stat in stats {
+        wtr.write_record([
+            stat.roi_name.clone(),
+            format!("{:.*}", p, stat.avg_vol_phase_start),
+            stat.phase_start.to_string(),
+            stat.phase_end.to_string(),
+            format!("{:.*}", p, stat.avg),
+            format!("{:.*}", p, stat.std_dev),
+            stat.n.to_string(),
+            format!("{:.*}", p, stat.avg_pct),
+            format!("{:.*}", p, stat.std_dev_pct),
+            stat.n_pct.to_string(),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Write the statistics as a semicolon delimited CSV and a Stata do-file which imports it,
+/// labels the variables and tabulates the statistics per ROI.
+///
+/// # Arguments
+///
+/// * `stats` - statistics to write
+/// * `dta_path` - path of the CSV file imported by the do-file
+/// * `do_path` - path of the do-file
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_stata_script(
+    stats: &[Stat],
+    dta_path: &str,
+    do_path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut file = std::fs::File::create(dta_path)?;
+    write_stats_csv(stats, &mut file)?;
+
+    let mut file = std::fs::File::create(do_path)?;
+    writeln!(
+        file,
+        "import delimited using \"{}\", delimiter(\";\") varnames(1) clear",
+        dta_path
+    )?;
+    let labels = [
+        ("roi", "Name of the ROI"),
+        ("volumephasestart", "Average volume at the start phase"),
+        ("phasestart", "Phase at which the initial data was acquired"),
+        ("phaseend", "Phase at which the last data was acquired"),
+        ("average", "Average volume difference"),
+        ("std_dev", "Standard deviation of the volume differences"),
+        ("n", "Number of data points"),
+        ("average_pct", "Average relative volume difference [%]"),
+        ("std_dev_pct", "Standard deviation of the relative volume differences [%]"),
+        ("n_pct", "Number of data points of the relative volume differences"),
+    ];
+    for (var, label) in labels {
+        writeln!(file, "label variable {} \"{}\"", var, label)?;
+    }
+    writeln!(file, "tabstat average std_dev n, by(roi)")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "Patient ID;GTV_phase_1;GTV_phase_2;GTV_phase_3;\
+        GTV_N_phase_1;GTV_N_phase_2;GTV_N_phase_3;PTV_DP_phase_1;PTV_DP_phase_2;PTV_DP_phase_3";
+
+    fn discover(content: &str) -> Result<Vec<Data>, Box<dyn Error>> {
+        let rdr = csv::ReaderBuilder::new()
+            .delimiter(b';')
+            .has_headers(true)
+            .from_reader(content.as_bytes());
+        discover_data(rdr)
+    }
+
+    #[test]
+    fn discover_data_matches_records_to_data() {
+        let content = format!(
+            "{}\nP1;10;8;6;3;2;1;100;90;80\nP2;20;15;12;;;;200;180;170\n",
+            HEADER
+        );
+        let records = Records::from_csv_str(&content, b';').unwrap();
+        let expected = records_to_data(&records.0);
+        let dataset = discover(&content).unwrap();
+        assert_eq!(dataset.len(), 3);
+        for (data, expected) in dataset.iter().zip(expected.iter()) {
+            assert_eq!(data.roi_name, expected.roi_name);
+            assert_eq!(data.vol_phase_1, expected.vol_phase_1);
+            assert_eq!(data.vol_phase_2, expected.vol_phase_2);
+            assert_eq!(data.vol_phase_3, expected.vol_phase_3);
+        }
+    }
+
+    #[test]
+    fn discover_data_novel_rois() {
+        let content = "Patient ID;CTV_phase_1;CTV_phase_2;CTV_phase_3;\
+            Lung_L_phase_1;Lung_L_phase_2;Lung_L_phase_3\n\
+            P1;10;8;6;1500;1450;1400\n";
+        let dataset = discover(content).unwrap();
+        let names: Vec<_> = dataset.iter().map(|d| d.roi_name.as_str()).collect();
+        assert_eq!(names, ["CTV", "Lung_L"]);
+        assert_eq!(dataset[1].vol_phase_1, [Some(1500.0)]);
+        assert_eq!(dataset[1].vol_phase_3, [Some(1400.0)]);
+    }
+
+    #[test]
+    fn discover_data_two_phases() {
+        let content = "Patient ID;CTV_phase_1;CTV_phase_2\nP1;10;8\nP2;20;15\n";
+        let dataset = discover(content).unwrap();
+        assert_eq!(dataset[0].vol_phase_2, [Some(8.0), Some(15.0)]);
+        assert!(dataset[0].vol_phase_3.is_empty());
+        let stats = dataset_to_stats(&dataset).unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].phase_end(), 2);
+    }
+
+    #[test]
+    fn discover_data_skips_empty_cells() {
+        let content = "Patient ID;CTV_phase_1;CTV_phase_2;Lung_L_phase_1;Lung_L_phase_2\n\
+            P1;10;;5;4\nP2;20;15;6;5\n";
+        let dataset = discover(content).unwrap();
+        assert_eq!(dataset[0].vol_phase_1, [Some(20.0)]);
+        assert_eq!(dataset[0].vol_phase_2, [Some(15.0)]);
+        assert_eq!(dataset[1].vol_phase_1, [Some(5.0), Some(6.0)]);
+    }
+
+    #[test]
+    fn discover_data_rejects_duplicate_columns() {
+        let content = "Patient ID;GTV_phase_1;GTV_phase_2;GTV_phase_1\nP1;10;8;10\n";
+        assert!(discover(content).is_err());
+    }
+
+    fn data(vol_phase_1: &[Option<f64>], vol_phase_2: &[Option<f64>]) -> Data {
+        let mut data = Data::new("GTV");
+        data.vol_phase_1 = vol_phase_1.to_vec();
+        data.vol_phase_2 = vol_phase_2.to_vec();
+        data.vol_phase_3 = vec![Some(1.0); vol_phase_1.len()];
+        data
+    }
+
+    #[test]
+    fn pct_skips_zero_start_volume() {
+        let data = data(
+            &[Some(0.0), Some(10.0), Some(20.0)],
+            &[Some(1.0), Some(8.0), Some(15.0)],
+        );
+        let stat = data.phase_1_to_2_stat().unwrap();
+        assert_eq!(stat.n(), 3);
+        assert_eq!(stat.n_pct(), 2);
+        assert!((stat.avg_pct() - -22.5).abs() < 1e-12);
+        assert!(stat.std_dev_pct().is_finite());
+    }
+
+    #[test]
+    fn pct_mixed_missing_data() {
+        let data = data(
+            &[Some(10.0), None, Some(0.0), Some(20.0)],
+            &[Some(8.0), Some(5.0), Some(3.0), None],
+        );
+        let stat = data.phase_1_to_2_stat().unwrap();
+        assert_eq!(stat.n(), 2);
+        assert_eq!(stat.n_pct(), 1);
+        assert!((stat.avg_pct() - -20.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn pct_zero_filled_without_valid_pairs() {
+        let data = data(&[Some(0.0), Some(0.0)], &[Some(1.0), Some(2.0)]);
+        let stat = data.phase_1_to_2_stat().unwrap();
+        assert_eq!(stat.n(), 2);
+        assert_eq!(stat.avg_pct(), 0.0);
+        assert_eq!(stat.std_dev_pct(), 0.0);
+        assert_eq!(stat.n_pct(), 0);
+        assert_eq!(avg_std_dev_pct([(f64::NAN, 1.0), (1.0, f64::NAN)]), (0.0, 0.0, 0));
+    }
+
+    #[test]
+    fn stats_csv_round_trip() {
+        let stats = vec![
+            Stat::builder()
+                .roi_name("GTV")
+                .avg_vol_phase_start(20.25)
+                .phase_start(1)
+                .phase_end(2)
+                .avg(11.0 / 6.0)
+                .std_dev(2.5)
+                .n(12)
+                .avg_pct(-12.5)
+                .std_dev_pct(4.0)
+                .n_pct(11)
+                .build()
+                .unwrap(),
+        ];
+        let path = std::env::temp_dir().join("c_art_2_volume_changes_round_trip.csv");
+        let mut file = std::fs::File::create(&path).unwrap();
+        write_stats_csv(&stats, &mut file).unwrap();
+        drop(file);
+        let read = Stat::from_csv_file(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let tolerance = 0.5 * 10f64.powi(-(CsvWriterOptions::default().float_precision as i32));
+        assert_eq!(read.len(), 1);
+        let (a, b) = (&stats[0], &read[0]);
+        assert_eq!(a, b);
+        assert_eq!(a.n(), b.n());
+        assert_eq!(a.n_pct(), b.n_pct());
+        for (x, y) in [
+            (a.avg_vol_phase_start(), b.avg_vol_phase_start()),
+            (a.avg(), b.avg()),
+            (a.std_dev(), b.std_dev()),
+            (a.avg_pct(), b.avg_pct()),
+            (a.std_dev_pct(), b.std_dev_pct()),
+        ] {
+            assert!((x - y).abs() <= tolerance, "{} != {}", x, y);
+        }
+    }
+
+    fn stat(avg: f64, std_dev: f64, n: usize) -> Stat {
+        Stat::builder()
+            .roi_name("GTV")
+            .avg_vol_phase_start(20.0)
+            .phase_start(1)
+            .phase_end(2)
+            .avg(avg)
+            .std_dev(std_dev)
+            .n(n)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn agrees_with_zero_tolerance_requires_equal_avg() {
+        let a = stat(4.0, 2.0, 10);
+        assert!(a.agrees_with(&stat(4.0, 3.0, 12), 0.0));
+        assert!(!a.agrees_with(&stat(4.0 + 1e-9, 3.0, 12), 0.0));
+    }
+
+    #[test]
+    fn agrees_with_tolerance() {
+        // combined standard deviation hypot(3, 4) = 5
+        let a = stat(0.0, 3.0, 10);
+        assert!(a.agrees_with(&stat(5.0, 4.0, 10), 1.0));
+        assert!(!a.agrees_with(&stat(5.1, 4.0, 10), 1.0));
+    }
+
+    fn records() -> Records {
+        Records::from(vec![
+            Record::synthetic("P1", [10.0, 8.0, 6.0], [3.0, 2.0, 1.0], [100.0, 90.0, 80.0]),
+            Record::synthetic("P2", [20.0, 15.0, 12.0], [4.0, 3.0, 2.0], [200.0, 180.0, 170.0]),
+        ])
+    }
+
+    #[test]
+    fn records_index() {
+        let mut records = records();
+        assert_eq!(records[1].patient_id, "P2");
+        records[1].patient_id = "P3".to_string();
+        assert_eq!(records[1].patient_id, "P3");
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds: the len is 2 but the index is 2")]
+    fn records_index_out_of_bounds() {
+        let records = records();
+        let _ = &records[2];
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds: the len is 2 but the index is 2")]
+    fn records_index_mut_out_of_bounds() {
+        let mut records = records();
+        records[2].patient_id = "P3".to_string();
+    }
+
+    #[test]
+    fn to_phase_pair_data_same_length() {
+        let data = data(
+            &[Some(10.0), None, Some(f64::NAN), Some(20.0), Some(30.0)],
+            &[Some(8.0), Some(5.0), Some(3.0), None, Some(24.0)],
+        );
+        let (start, end) = data.to_phase_pair_data(1, 2).unwrap();
+        assert_eq!(start.len(), end.len());
+        assert_eq!(start, [10.0, 30.0]);
+        assert_eq!(end, [8.0, 24.0]);
+        assert!(start.iter().chain(end.iter()).all(|v| !v.is_nan()));
+    }
+
+    #[test]
+    fn hedges_g_smaller_than_cohens_d() {
+        for n in [2, 3, 5, 10, 50] {
+            for avg in [-4.0, 0.5, 4.0] {
+                let stat = stat(avg, 2.0, n);
+                let d = stat.cohens_d().unwrap();
+                let g = stat.hedges_g().unwrap();
+                assert!(g < d.abs(), "n = {}, avg = {}", n, avg);
+                assert!(g.abs() < d.abs(), "n = {}, avg = {}", n, avg);
+            }
+        }
+        assert_eq!(stat(4.0, 2.0, 1).hedges_g(), None);
+    }
+
+    #[test]
+    fn format_ci_matches_confidence_interval() {
+        let stat = stat(4.0, 2.0, 10);
+        for alpha in [0.01, 0.05, 0.1] {
+            let (lower, upper) = stat.confidence_interval(alpha).unwrap();
+            assert_eq!(stat.format_ci(alpha), format!("[{}, {}]", lower, upper));
+        }
+        // t(0.975, 9) = 2.2622
+        let (lower, upper) = stat.confidence_interval(0.05).unwrap();
+        assert!((upper - 4.0 - 2.2622 * 2.0 / 10f64.sqrt()).abs() < 1e-3);
+        assert!((4.0 - lower - (upper - 4.0)).abs() < 1e-12);
+        assert_eq!(stat.format_ci(1.5), "[NaN, NaN]");
+    }
+
+    #[test]
+    fn rename_roi_stats_use_new_name() {
+        let mut dataset = DataSet::from(records_to_data(&records().0));
+        dataset.rename_roi("GTV", "GTVprimary").unwrap();
+        let stats = dataset_to_stats(&dataset.0).unwrap();
+        assert!(stats.iter().any(|s| s.roi_name() == "GTVprimary"));
+        assert!(stats.iter().all(|s| s.roi_name() != "GTV"));
+        assert!(dataset.rename_roi("GTV", "CTV").is_err());
+        assert!(dataset.rename_roi("GTVprimary", "GTV_N").is_err());
+    }
+
+    #[test]
+    fn icc_matrix_display_aligned() {
+        let table = IccMatrix::default().to_string();
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines.iter().all(|l| l.len() == lines[0].len()));
+        assert!(lines[0].ends_with("phase 3"));
+    }
+
+    #[test]
+    fn data_quality_report_unit_inconsistencies() {
+        let mut records = records();
+        records.0.push(Record::synthetic(
+            "P3",
+            [15.0, 12.0, 9.0],
+            [3.5, 2.5, 1.5],
+            [150.0, 140.0, 130.0],
+        ));
+        let report = records.data_quality_report();
+        assert!(report.unit_inconsistencies.is_empty());
+        assert!(report.to_string().contains("\n0 volumes likely reported in the wrong unit"));
+
+        records[2].gtv_phase_i = Some(15000.0);
+        let report = records.data_quality_report();
+        assert_eq!(report.unit_inconsistencies, records.detect_unit_inconsistencies());
+        assert_eq!(report.unit_inconsistencies.len(), 1);
+        assert!(report.to_string().contains("Patient [P3] GTV phase 1: volume 15000"));
+    }
+
+    #[test]
+    fn efficient_data_phases() {
+        let mut data = Data::new("GTV");
+        data.add_vol(Some(10.0), Some(8.0), Some(6.0));
+        data.add_vol(Some(20.0), Some(15.0), Some(12.0));
+        let efficient = EfficientData::from(data.clone());
+        let phases: Vec<_> = efficient.phases().collect();
+        assert_eq!(phases.len(), 3);
+        for ((p, v), (q, w)) in phases.iter().zip(data.phases()) {
+            assert_eq!(*p, q);
+            let w: Vec<_> = w.iter().map(|x| x.unwrap()).collect();
+            assert_eq!(v.to_vec(), w);
+        }
+    }
+
+    #[test]
+    fn sign_consistency_with_zero() {
+        assert!(stat(0.0, 1.0, 5).sign_consistency_with(&stat(-0.0, 1.0, 5)));
+        assert!(!stat(0.0, 1.0, 5).sign_consistency_with(&stat(2.0, 1.0, 5)));
+        assert!(!stat(-0.0, 1.0, 5).sign_consistency_with(&stat(-2.0, 1.0, 5)));
+        assert!(stat(3.0, 1.0, 5).sign_consistency_with(&stat(2.0, 1.0, 5)));
+        assert!(!stat(3.0, 1.0, 5).sign_consistency_with(&stat(-2.0, 1.0, 5)));
+    }
+
+    #[cfg(feature = "polars")]
+    #[test]
+    fn polars_dataframe_row_per_patient() {
+        let records = Records::from(vec![
+            Record::synthetic_partial(
+                "P1",
+                Some([10.0, 8.0, 6.0]),
+                None,
+                Some([100.0, 90.0, 80.0]),
+            ),
+            Record::synthetic("P2", [20.0, 15.0, 12.0], [4.0, 3.0, 2.0], [200.0, 180.0, 170.0]),
+        ]);
+        let df = to_polars_dataframe(&records).unwrap();
+        assert_eq!(df.height(), 2);
+        assert_eq!(df.width(), 10);
+        let ids = df.column("Patient ID").unwrap().str().unwrap();
+        assert_eq!(ids.get(1), Some("P2"));
+        let gtv_n = df.column("GTV_N_phase_1").unwrap().f64().unwrap();
+        assert_eq!(gtv_n.get(0), None);
+        assert_eq!(gtv_n.get(1), Some(4.0));
+        let gtv = df.column("GTV_phase_1").unwrap().f64().unwrap();
+        assert_eq!(gtv.get(0), Some(10.0));
+        assert_eq!(gtv.get(1), Some(20.0));
+    }
+
+    #[test]
+    fn magnitude_category_default_thresholds() {
+        let category = |avg: f64| stat(avg, 1.0, 5).magnitude_category();
+        assert_eq!(category(1.0), Some(MagnitudeCategory::Negligible));
+        assert_eq!(category(-3.0), Some(MagnitudeCategory::Minor));
+        assert_eq!(category(12.0), Some(MagnitudeCategory::Major));
+        assert_eq!(category(-25.0), Some(MagnitudeCategory::Extreme));
+        assert_eq!(MagnitudeThresholds::default().classify(f64::NAN), None);
+    }
+
+    #[test]
+    fn build_checks_the_invariants_of_is_valid() {
+        let builder = || {
+            Stat::builder()
+                .roi_name("GTV")
+                .phase_start(1)
+                .phase_end(2)
+                .avg(1.0)
+                .std_dev(1.0)
+                .n(3)
+        };
+        assert!(builder().avg_vol_phase_start(20.0).build().unwrap().is_valid());
+        assert!(builder().avg_vol_phase_start(-1.0).build().is_err());
+        assert!(builder().avg_vol_phase_start(f64::NAN).build().is_err());
+        assert!(builder().avg_vol_phase_start(20.0).std_dev(-1.0).build().is_err());
+    }
+
+    #[test]
+    fn unit_inconsistencies_ignore_zero_volumes() {
+        let records = Records::from(vec![
+            Record::synthetic("P1", [10.0, 8.0, 6.0], [0.0, 0.0, 0.0], [100.0, 90.0, 80.0]),
+            Record::synthetic("P2", [20.0, 15.0, 12.0], [0.0, 0.0, 0.0], [200.0, 180.0, 170.0]),
+            Record::synthetic("P3", [15.0, 12.0, 9.0], [4.0, 3.0, 2.0], [150.0, 140.0, 130.0]),
+        ]);
+        assert!(records.detect_unit_inconsistencies().is_empty());
+
+        let zero = Records::from(vec![Record::synthetic(
+            "P1",
+            [10.0, 8.0, 6.0],
+            [0.0, 0.0, 0.0],
+            [100.0, 90.0, 80.0],
+        )]);
+        assert!(zero.detect_unit_inconsistencies().is_empty());
+    }
+
+    #[test]
+    fn rename_roi_errors() {
+        let mut dataset = DataSet::from(records_to_data(&records().0));
+        dataset.rename_roi("GTV", "GTV").unwrap();
+        assert_eq!(dataset.0[0].roi_name, "GTV");
+        let e = dataset.rename_roi("CTV", "GTV_N").unwrap_err();
+        assert!(e.to_string().contains("Unknown ROI [CTV]"));
+        let e = dataset.rename_roi("GTV", "GTV_N").unwrap_err();
+        assert!(e.to_string().contains("Duplicate ROI [GTV_N]"));
+    }
+
+    #[test]
+    fn efficient_data_matches_data() {
+        let mut data = Data::new("GTV");
+        data.add_vol(Some(10.0), Some(8.0), Some(6.0));
+        data.add_vol(Some(20.0), Some(15.0), Some(12.0));
+        data.add_vol(Some(30.0), Some(29.0), Some(20.0));
+        let efficient = EfficientData::from(data.clone());
+        assert_eq!(
+            efficient.top_n_patients_by_shrinkage(2, 1, 2).unwrap(),
+            data.top_n_patients_by_shrinkage(2, 1, 2).unwrap()
+        );
+        assert_eq!(
+            efficient.cumulative_volume_change_distribution(1, 3).unwrap(),
+            data.cumulative_volume_change_distribution(1, 3).unwrap()
+        );
+
+        let mut efficient = EfficientData::new("GTV_N");
+        efficient.vol_phase_1 = vec![4.0, f64::NAN];
+        efficient.vol_phase_2 = vec![3.0, 2.0];
+        efficient.vol_phase_3 = vec![2.0, 1.0];
+        let data = Data::from(efficient);
+        assert_eq!(data.vol_phase_1, [Some(4.0), None]);
+        assert_eq!(data.vol_phase_3, [Some(2.0), Some(1.0)]);
+    }
+}
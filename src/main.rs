@@ -1,10 +1,14 @@
 #![allow(dead_code)]
 
-use c_art_2_volume_changes::{read_csv, records_to_data, dataset_to_stats};
+use c_art_2_volume_changes::{
+    read_csv_with_options, records_to_data, dataset_to_stats, MagnitudeThresholds,
+    PipelineOptions,
+};
 use clap::Parser;
 use std::error::Error;
 use std::fs::File;
 use simple_logger::SimpleLogger;
+use log::{info, warn};
 
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about=None)]
@@ -15,18 +19,102 @@ struct Args {
     /// JSON file where the results are written to.
     #[arg(short, long, default_value="volume_changes_stats.json")]
     results: String,
+    /// ROI for which the empirical cumulative distribution of the volume changes is written to
+    /// `<ROI>_ecdf_phase_<start>_<end>.csv`.
+    #[arg(long, value_name="ROI")]
+    ecdf: Option<String>,
+    /// Warn about volumes larger than MULTIPLIER times the median volume of the ROI, which are
+    /// likely reported in the wrong unit.
+    #[arg(long, value_name="MULTIPLIER", num_args=0..=1, default_missing_value="10")]
+    unit_check: Option<f64>,
+    /// Log the magnitude category of the average volume changes using the comma separated lower
+    /// bounds of the minor, moderate, major and extreme categories [cc], e.g. `2,5,10,20`.
+    #[arg(long, value_name="THRESHOLDS", value_parser=parse_magnitude_thresholds)]
+    magnitude_thresholds: Option<MagnitudeThresholds>,
+    /// Print the Bland-Altman analysis of the volumes between the consecutive phases.
+    #[arg(long)]
+    bland_altman: bool,
+}
+
+fn parse_magnitude_thresholds(s: &str) -> Result<MagnitudeThresholds, String> {
+    s.parse().map_err(|e: Box<dyn Error>| e.to_string())
+}
+
+impl TryFrom<Args> for PipelineOptions {
+    type Error = Box<dyn Error>;
+
+    fn try_from(args: Args) -> Result<Self, Self::Error> {
+        PipelineOptions::builder().file(&args.file).build()
+    }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     SimpleLogger::new().init().unwrap();
     let args = Args::parse();
+    let opts = PipelineOptions::try_from(args.clone())?;
 
-    let records = read_csv(&args.file)?;
-    let dataset = records_to_data(&records);
+    let records = read_csv_with_options(&opts.file, &opts.read_options())?;
+    if let Some(multiplier) = args.unit_check {
+        for u in records.detect_unit_inconsistencies_with_multiplier(multiplier) {
+            warn!(
+                "Patient [{}] {} phase {}: volume {} exceeds the expected range [{}, {}].",
+                u.patient_id, u.roi, u.phase, u.volume, u.expected_range.0, u.expected_range.1
+            );
+        }
+    }
+    let dataset = records_to_data(&records.0);
     let stats = dataset_to_stats(&dataset)?;
 
-    let file = File::create(args.results)?;
+    let file = File::create(&args.results)?;
     serde_json::to_writer_pretty(file, &stats)?;
 
+    if args.bland_altman {
+        for data in &dataset {
+            for (start, end) in [(1, 2), (2, 3)] {
+                match data.bland_altman_analysis(start, end) {
+                    Ok(ba) => println!("{}", ba),
+                    Err(e) => warn!(
+                        "Skipping the Bland-Altman analysis of {} phase {} to {}: {}",
+                        data.roi_name, start, end, e
+                    ),
+                }
+            }
+        }
+    }
+
+    if let Some(thresholds) = &args.magnitude_thresholds {
+        for stat in &stats {
+            match stat.magnitude_category_with(thresholds) {
+                Some(category) => info!(
+                    "{} phase {} to {}: {:?} volume change [{}].",
+                    stat.roi_name(),
+                    stat.phase_start(),
+                    stat.phase_end(),
+                    category,
+                    stat.avg()
+                ),
+                None => warn!(
+                    "{} phase {} to {}: no magnitude category for volume change [{}].",
+                    stat.roi_name(),
+                    stat.phase_start(),
+                    stat.phase_end(),
+                    stat.avg()
+                ),
+            }
+        }
+    }
+
+    if let Some(roi) = &args.ecdf {
+        let data = dataset
+            .iter()
+            .find(|data| data.roi_name == *roi)
+            .ok_or_else(|| format!("Unknown ROI [{}] for the ECDF.", roi))?;
+        for (start, end) in [(1, 2), (2, 3)] {
+            let cdf = data.cumulative_volume_change_distribution(start, end)?;
+            let file = File::create(format!("{}_ecdf_phase_{}_{}.csv", roi, start, end))?;
+            cdf.to_csv_writer(file)?;
+        }
+    }
+
     Ok(())
 }
@@ -0,0 +1,117 @@
+//! Protocol Buffers messages matching `proto/c_art_2_volume_changes.proto`.
+
+use crate::{Record, Stat};
+use prost::Message;
+#[cfg(not(target_arch = "wasm32"))]
+use std::error::Error;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs::File;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::Write;
+
+/// ROI volumes per treatment phase of a patient.
+#[derive(Clone, PartialEq, Message)]
+pub struct RecordProto {
+    #[prost(string, tag = "1")]
+    pub patient_id: String,
+    #[prost(double, optional, tag = "2")]
+    pub gtv_phase_1: Option<f64>,
+    #[prost(double, optional, tag = "3")]
+    pub gtv_phase_2: Option<f64>,
+    #[prost(double, optional, tag = "4")]
+    pub gtv_phase_3: Option<f64>,
+    #[prost(double, optional, tag = "5")]
+    pub gtv_n_phase_1: Option<f64>,
+    #[prost(double, optional, tag = "6")]
+    pub gtv_n_phase_2: Option<f64>,
+    #[prost(double, optional, tag = "7")]
+    pub gtv_n_phase_3: Option<f64>,
+    #[prost(double, optional, tag = "8")]
+    pub ptv_dp_phase_1: Option<f64>,
+    #[prost(double, optional, tag = "9")]
+    pub ptv_dp_phase_2: Option<f64>,
+    #[prost(double, optional, tag = "10")]
+    pub ptv_dp_phase_3: Option<f64>,
+}
+
+/// Statistical data per ROI and phase change.
+#[derive(Clone, PartialEq, Message)]
+pub struct StatProto {
+    #[prost(string, tag = "1")]
+    pub roi: String,
+    #[prost(double, tag = "2")]
+    pub volume_phase_start: f64,
+    #[prost(int32, tag = "3")]
+    pub phase_start: i32,
+    #[prost(int32, tag = "4")]
+    pub phase_end: i32,
+    #[prost(double, tag = "5")]
+    pub average: f64,
+    #[prost(double, tag = "6")]
+    pub std_dev: f64,
+    #[prost(uint64, tag = "7")]
+    pub n: u64,
+    #[prost(double, tag = "8")]
+    pub average_pct: f64,
+    #[prost(double, tag = "9")]
+    pub std_dev_pct: f64,
+    #[prost(uint64, tag = "10")]
+    pub n_pct: u64,
+}
+
+/// List of statistics.
+#[derive(Clone, PartialEq, Message)]
+pub struct StatsProto {
+    #[prost(message, repeated, tag = "1")]
+    pub stats: Vec<StatProto>,
+}
+
+impl From<&Record> for RecordProto {
+    fn from(r: &Record) -> Self {
+        Self {
+            patient_id: r.patient_id.clone(),
+            gtv_phase_1: r.gtv_phase_i,
+            gtv_phase_2: r.gtv_phase_ii,
+            gtv_phase_3: r.gtv_phase_iii,
+            gtv_n_phase_1: r.gtv_n_phase_i,
+            gtv_n_phase_2: r.gtv_n_phase_ii,
+            gtv_n_phase_3: r.gtv_n_phase_iii,
+            ptv_dp_phase_1: r.ptv_dp_phase_i,
+            ptv_dp_phase_2: r.ptv_dp_phase_ii,
+            ptv_dp_phase_3: r.ptv_dp_phase_iii,
+        }
+    }
+}
+
+impl From<&Stat> for StatProto {
+    fn from(s: &Stat) -> Self {
+        Self {
+            roi: s.roi_name.clone(),
+            volume_phase_start: s.avg_vol_phase_start,
+            phase_start: s.phase_start,
+            phase_end: s.phase_end,
+            average: s.avg,
+            std_dev: s.std_dev,
+            n: s.n as u64,
+            average_pct: s.avg_pct,
+            std_dev_pct: s.std_dev_pct,
+            n_pct: s.n_pct as u64,
+        }
+    }
+}
+
+/// Write the statistics as a Protocol Buffers encoded `Stats` message.
+///
+/// # Arguments
+///
+/// * `stats` - statistics to write
+/// * `path` - path of the output file
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_stats_proto(stats: &[Stat], path: &str) -> Result<(), Box<dyn Error>> {
+    let msg = StatsProto {
+        stats: stats.iter().map(StatProto::from).collect(),
+    };
+    let mut file = File::create(path)?;
+    file.write_all(&msg.encode_to_vec())?;
+    Ok(())
+}
@@ -0,0 +1,32 @@
+//! WebAssembly entry point.
+
+use crate::{dataset_to_stats, records_to_data, Records, Stat};
+use std::error::Error;
+use wasm_bindgen::prelude::*;
+
+fn run_pipeline(csv_content: &str, delimiter: char) -> Result<Vec<Stat>, Box<dyn Error>> {
+    if !delimiter.is_ascii() {
+        return Err(format!("Expected an ASCII delimiter [{}].", delimiter).into());
+    }
+    let records = Records::from_csv_str(csv_content, delimiter as u8)?;
+    let dataset = records_to_data(&records.0);
+    dataset_to_stats(&dataset)
+}
+
+/// Compute the statistics of the volume changes from CSV content.
+///
+/// Returns the statistics as a JSON string, or the error message on failure.
+///
+/// # Arguments
+///
+/// * `csv_content` - CSV content, including the header
+/// * `delimiter` - delimiter used between the values
+#[wasm_bindgen]
+pub fn run_pipeline_wasm(csv_content: &str, delimiter: char) -> JsValue {
+    let json = run_pipeline(csv_content, delimiter)
+        .and_then(|stats| serde_json::to_string(&stats).map_err(|e| e.into()));
+    match json {
+        Ok(s) => JsValue::from_str(&s),
+        Err(e) => JsValue::from_str(&e.to_string()),
+    }
+}